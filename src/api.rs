@@ -1,21 +1,79 @@
 //! Generic functions and structs for the API
 //!
 //! This module contains the generic functions and structs for the API.
-//! These are used by the `Client`, and public for the whole crate
+//! These are used by the clients, and public for the whole crate
 
 use chrono::prelude::*;
-use std::fmt::Debug;
-
-use reqwest::StatusCode;
-use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, LemonError};
+/// Module for interacting with the market data related endpoints
+pub mod market_data;
+/// Module for interacting with the order related endpoints
+pub mod orders;
+/// Module for interacting with the account and position related endpoints
+pub mod trading;
+
+/// Defines an enum of the string values the API sends for a field, with a fallback for
+/// values that are not in the list.
+///
+/// The enum gets `as_str`, `From<&str>`, `Display` and serde implementations that use the
+/// strings of the API. Values added to the API later end up in the `Other` variant, so they
+/// never break deserialization.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A value this crate does not know, as sent by the API
+            Other(String),
+        }
+
+        impl $name {
+            /// The value as sent by the API
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    _ => $name::Other(value.to_string()),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
 
-/// Module for interacting with the account related endpoints
-mod market_data;
-mod orders;
-mod trading;
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Ok($name::from(value.as_str()))
+            }
+        }
+    };
+}
+pub(crate) use string_enum;
 
 /// Generic struct for Endpoints that returns pagination information alongside data
 ///
@@ -36,7 +94,7 @@ pub struct PaginationResponse<T> {
     pub time: DateTime<Utc>,
     /// The status of the request.
     pub status: Option<String>,
-    /// The mode of the request. Can be paper, live, or market_data
+    /// The mode of the request. Can be paper, money, or market_data
     pub mode: Option<Mode>,
     /// The actual results of the query. Depends upon the given generics
     pub results: Option<Vec<T>>,
@@ -58,7 +116,6 @@ pub struct Response {
     /// Timestamp of your request
     pub time: String,
     /// Environment the request was placed in: "paper" or "money"
-    // TODO: Make this an enum
     pub mode: Mode,
     /// Status of the request. Returns 'ok' if successful
     pub status: String,
@@ -70,7 +127,6 @@ pub struct GenericResponse<T> {
     /// Timestamp of your request
     pub time: DateTime<Utc>,
     /// Environment the request was placed in: "paper" or "money"
-    // TODO: Make this an enum
     pub mode: Mode,
     /// Status of the request.
     pub status: String,
@@ -78,62 +134,63 @@ pub struct GenericResponse<T> {
     pub results: Option<T>,
 }
 
-/// Trading mode
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(rename_all = "lowercase")]
-pub enum Mode {
-    /// Paper trading mode
-    Paper,
-    /// Live trading mode
-    Live,
-    /// Market data mode
-    MarketData,
-}
-
-impl std::fmt::Display for Mode {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match *self {
-            Mode::Paper => write!(f, "paper"),
-            Mode::Live => write!(f, "live"),
-            Mode::MarketData => write!(f, "market_data"),
-        }
+string_enum! {
+    /// Trading mode
+    Mode {
+        /// Paper trading mode
+        Paper => "paper",
+        /// Live trading mode, with real money
+        Live => "money",
+        /// Market data mode
+        MarketData => "market_data",
     }
 }
 
-/// Traits with API methods that are common across all calls
-pub(crate) trait Requests {
-    /// Generic get request
-    fn get<T: DeserializeOwned + Debug>(&self, path: &str) -> Result<T, Error>;
-    /// Generic get request with query parameters
-    fn get_with_query<T: DeserializeOwned + Debug, Q: IntoIterator + Serialize>(
-        &self,
-        path: &str,
-        query: Q,
-    ) -> Result<T, Error>;
-
-    /// Generic post request
-    fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: B) -> Result<T, Error>;
-
-    /// Generic delete request
-    fn delete<T: DeserializeOwned>(&self, path: &str, path_param: &str) -> Result<T, Error>;
-
-    /// Crate wide function to handle responses and errors
-    fn response_handler<T: DeserializeOwned>(
-        &self,
-        response: reqwest::blocking::Response,
-    ) -> Result<T, Error> {
-        match response.status() {
-            StatusCode::OK => Ok(response.json::<T>()?),
-            _s => {
-                let message = response.json::<LemonError>()?;
-                Err(Error::Str(message.to_string()))
-            }
+/// Implements endpoint methods on a blocking client and its async counterpart.
+///
+/// Every method body evaluates to the [`Request`](crate::request::Request) for the call, and
+/// the declared return type is what the response deserializes into. The blocking client gets
+/// a method returning `Result<T, Error>`, the async client the same method as an `async fn`.
+macro_rules! endpoints {
+    (TradingClient; $($endpoints:tt)*) => {
+        $crate::api::endpoints!(
+            @impl client, TradingClient, AsyncTradingClient;
+            $($endpoints)*
+        );
+    };
+    (DataClient; $($endpoints:tt)*) => {
+        $crate::api::endpoints!(
+            @impl data_client, DataClient, AsyncDataClient;
+            $($endpoints)*
+        );
+    };
+    (
+        @impl $module:ident, $blocking:ident, $async:ident;
+        $(
+            $(#[$meta:meta])*
+            $vis:vis fn $name:ident(&$this:ident $(, $arg:ident: $ty:ty)* $(,)?) -> $ret:ty $body:block
+        )*
+    ) => {
+        impl<Tr: $crate::transport::Transport> $crate::$module::$blocking<Tr> {
+            $(
+                $(#[$meta])*
+                $vis fn $name(&$this $(, $arg: $ty)*) -> Result<$ret, $crate::error::Error> {
+                    let request: $crate::request::Request<$ret> = $body;
+                    $this.send(request)
+                }
+            )*
         }
-    }
-    /// Crate wide function to handle query params
-    fn get_query_string<Q: Serialize>(query: Q, query_vector: &mut Vec<String>) {
-        if let Ok(query_string) = serde_urlencoded::to_string(&query) {
-            query_vector.push(query_string);
+
+        #[cfg(feature = "async")]
+        impl<Tr: $crate::transport::AsyncTransport> $crate::$module::$async<Tr> {
+            $(
+                $(#[$meta])*
+                $vis async fn $name(&$this $(, $arg: $ty)*) -> Result<$ret, $crate::error::Error> {
+                    let request: $crate::request::Request<$ret> = $body;
+                    $this.send(request).await
+                }
+            )*
         }
-    }
+    };
 }
+pub(crate) use endpoints;
@@ -1,2 +1,4 @@
-mod instruments;
-mod venues;
+/// Module for interacting with the instrument related endpoints
+pub mod instruments;
+/// Module for interacting with the venue related endpoints
+pub mod venues;
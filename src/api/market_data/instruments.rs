@@ -1,67 +1,87 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{PaginationResponse, Requests};
-use crate::client::TradingClient;
-use crate::{data_client::DataClient, error::Error, query_tuple};
+use crate::api::{endpoints, string_enum, PaginationResponse};
+use crate::identifiers::{Isin, Mic, Wkn};
+use crate::request::Request;
 
+/// A venue an instrument is traded at
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InstrumentVenue {
+    /// Name of the venue
     pub name: String,
+    /// Title of the venue
     pub title: String,
-    pub mic: String,
+    /// Market Identifier Code of the venue
+    pub mic: Mic,
+    /// Whether the venue is currently open
     pub is_open: bool,
+    /// Whether the instrument is tradable at the venue
     pub tradable: bool,
+    /// Currency the instrument is traded in at the venue
     pub currency: String,
 }
 
+/// Information about an instrument
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InstrumentInfo {
-    pub isin: Option<String>,
-    pub wkn: Option<String>,
+    /// ISIN of the instrument
+    pub isin: Option<Isin>,
+    /// WKN of the instrument
+    pub wkn: Option<Wkn>,
+    /// Name of the instrument
     pub name: Option<String>,
+    /// Title of the instrument
     pub title: Option<String>,
+    /// Symbol of the instrument
     pub symbol: Option<String>,
+    /// The type of the instrument, e.g. "stock" or "etf"
     #[serde(rename = "type")]
-    pub instrument_type: Option<String>,
+    pub instrument_type: Option<InstrumentType>,
+    /// Venues the instrument is traded at
     pub venues: Option<Vec<InstrumentVenue>>,
 }
 
-impl DataClient {
+string_enum! {
+    /// The type of an instrument
+    InstrumentType {
+        /// A share of a company
+        Stock => "stock",
+        /// A bond
+        Bond => "bond",
+        /// An actively managed fund
+        Fund => "fund",
+        /// An exchange traded fund
+        Etf => "etf",
+        /// A warrant
+        Warrant => "warrant",
+    }
+}
+
+endpoints! {
+    DataClient;
+
     /// Get a list of instruments.
     pub fn get_instruments(
         &self,
-        isin: Option<String>,
+        isin: Option<Isin>,
         search: Option<String>,
-        instrument_type: Option<String>,
-    ) -> Result<PaginationResponse<InstrumentInfo>, Error> {
+        instrument_type: Option<InstrumentType>,
+    ) -> PaginationResponse<InstrumentInfo> {
         const PATH: &str = "instruments/";
 
-        // Build query
-        let mut query: Vec<String> = vec![];
-        TradingClient::get_query_string(query_tuple!(isin), &mut query);
-        TradingClient::get_query_string(query_tuple!(search), &mut query);
-        TradingClient::get_query_string(query_tuple!(instrument_type), &mut query);
-
-        let resp =
-            self.get_with_query::<PaginationResponse<InstrumentInfo>, Vec<String>>(PATH, query);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        let query = (("isin", isin), ("search", search), ("type", instrument_type));
+        Request::get(PATH).query(&query)?
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::data_client::DataClient;
-
-    use std::env;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_instruments() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_DATA_API_KEY").unwrap();
-        let client = DataClient::new(api_key);
+        let server = MockServer::start().unwrap();
+        let client = server.data_client();
         let stock_name = "Aker";
         let _instruments = client
             .get_instruments(None, Some(stock_name.to_string()), None)
@@ -1,51 +1,178 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{PaginationResponse, Requests};
-use crate::data_client::DataClient;
+use crate::api::orders::MAX_EXPIRY_DAYS;
+use crate::api::{endpoints, PaginationResponse};
 use crate::error::Error;
+use crate::identifiers::Mic;
+use crate::request::Request;
 use chrono::prelude::*;
+use chrono_tz::Tz;
 
+/// Opening hours of a venue
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OpeningHours {
+    /// Time the venue opens, e.g. "08:00"
     pub start: String,
+    /// Time the venue closes, e.g. "22:00"
     pub end: String,
+    /// Timezone of the opening hours, e.g. "Europe/Berlin"
     pub timezone: String,
 }
 
+/// Information about a venue
 #[derive(Serialize, Deserialize, Debug)]
 pub struct VenueData {
+    /// Name of the venue
     pub name: String,
+    /// Title of the venue
     pub title: String,
-    pub mic: String,
+    /// Market Identifier Code of the venue
+    pub mic: Mic,
+    /// Whether the venue is currently open
     pub is_open: bool,
+    /// Opening hours of the venue
     pub opening_hours: OpeningHours,
+    /// Upcoming days the venue is open
     pub opening_days: Vec<NaiveDate>,
 }
 type VenueDataPagination = PaginationResponse<VenueData>;
 
-impl DataClient {
+impl OpeningHours {
+    /// The time the venue opens, in its timezone
+    pub fn open_time(&self) -> Result<NaiveTime, Error> {
+        NaiveTime::parse_from_str(&self.start, "%H:%M")
+            .map_err(|e| Error::Str(format!("invalid opening time {:?}: {}", self.start, e)))
+    }
+
+    /// The time the venue closes, in its timezone
+    pub fn close_time(&self) -> Result<NaiveTime, Error> {
+        NaiveTime::parse_from_str(&self.end, "%H:%M")
+            .map_err(|e| Error::Str(format!("invalid closing time {:?}: {}", self.end, e)))
+    }
+
+    /// The timezone of the opening hours
+    pub fn tz(&self) -> Result<Tz, Error> {
+        self.timezone
+            .parse()
+            .map_err(|e| Error::Str(format!("invalid timezone {:?}: {}", self.timezone, e)))
+    }
+}
+
+/// The range of expiry dates an order can be placed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryWindow {
+    /// The end of the next trading session. Orders expiring earlier are rejected
+    pub earliest: NaiveDate,
+    /// The last day of the requested horizon, but no later than the API allows
+    pub latest: NaiveDate,
+}
+
+impl VenueData {
+    /// The expiry dates that keep an order alive for the next `trading_days` sessions of the
+    /// venue.
+    ///
+    /// The current session counts if the venue has not closed yet, in its own timezone.
+    /// If the venue has fewer upcoming opening days than requested, the window ends at the
+    /// last one. `None` if the venue does not open again.
+    pub fn expiry_window(&self, trading_days: usize) -> Result<Option<ExpiryWindow>, Error> {
+        self.expiry_window_at(Utc::now(), trading_days)
+    }
+
+    /// [`VenueData::expiry_window`] as of `now`
+    pub(crate) fn expiry_window_at(
+        &self,
+        now: DateTime<Utc>,
+        trading_days: usize,
+    ) -> Result<Option<ExpiryWindow>, Error> {
+        let close = self.opening_hours.close_time()?;
+        let local = now.with_timezone(&self.opening_hours.tz()?);
+        let today = local.date_naive();
+        let mut sessions: Vec<NaiveDate> = self
+            .opening_days
+            .iter()
+            .copied()
+            .filter(|day| *day > today || (*day == today && local.time() < close))
+            .collect();
+        sessions.sort_unstable();
+        sessions.dedup();
+
+        let Some(&earliest) = sessions.first() else {
+            return Ok(None);
+        };
+        let last = trading_days.clamp(1, sessions.len()) - 1;
+        let max = now.date_naive() + chrono::Duration::days(MAX_EXPIRY_DAYS);
+        Ok(Some(ExpiryWindow {
+            earliest,
+            latest: sessions[last].min(max).max(earliest),
+        }))
+    }
+}
+
+endpoints! {
+    DataClient;
+
     /// Get a list of venues.
-    pub fn get_venues(&self) -> Result<VenueDataPagination, Error> {
+    pub fn get_venues(&self) -> VenueDataPagination {
         const PATH: &str = "venues/";
-        let resp = self.get::<VenueDataPagination>(PATH);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        Request::get(PATH)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::data_client::DataClient;
+    use chrono::prelude::*;
 
-    use std::env;
+    use crate::cassette::{fixture, ReplayTransport};
+    use crate::data_client::DataClient;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_venues() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_DATA_API_KEY").unwrap();
-        let client = DataClient::new(api_key);
+        let server = MockServer::start().unwrap();
+        let client = server.data_client();
         let _venues = client.get_venues().unwrap();
     }
+
+    #[test]
+    fn test_venue_payload_from_cassette() {
+        let replay = ReplayTransport::load(fixture("venues.json")).unwrap();
+        let client = DataClient::builder("key")
+            .build_with_transport(replay)
+            .unwrap();
+        let venues = client.get_venues().unwrap();
+        let venue = &venues.results.unwrap()[0];
+        assert_eq!(venue.mic, "XMUN");
+        assert_eq!(venue.opening_days.len(), 3);
+    }
+
+    #[test]
+    fn test_expiry_window_follows_the_venue_timezone() {
+        let day = |d| NaiveDate::from_ymd_opt(2022, 3, d).unwrap();
+        let venue = super::VenueData {
+            name: "Gettex".to_string(),
+            title: "Gettex".to_string(),
+            mic: "XMUN".parse().unwrap(),
+            is_open: false,
+            opening_hours: super::OpeningHours {
+                start: "08:00".to_string(),
+                end: "22:00".to_string(),
+                timezone: "Europe/Berlin".to_string(),
+            },
+            opening_days: vec![day(4), day(7), day(8), day(9)],
+        };
+        // 20:30 UTC is 21:30 in Berlin, so Friday's session is still open
+        let friday_evening = Utc.with_ymd_and_hms(2022, 3, 4, 20, 30, 0).unwrap();
+        let window = venue.expiry_window_at(friday_evening, 3).unwrap().unwrap();
+        assert_eq!((window.earliest, window.latest), (day(4), day(8)));
+
+        // 21:30 UTC is 22:30 in Berlin, after the close
+        let friday_night = Utc.with_ymd_and_hms(2022, 3, 4, 21, 30, 0).unwrap();
+        let window = venue.expiry_window_at(friday_night, 3).unwrap().unwrap();
+        assert_eq!((window.earliest, window.latest), (day(7), day(9)));
+        let window = venue.expiry_window_at(friday_night, 10).unwrap().unwrap();
+        assert_eq!(window.latest, day(9));
+
+        let later = Utc.with_ymd_and_hms(2022, 3, 10, 9, 0, 0).unwrap();
+        assert_eq!(venue.expiry_window_at(later, 1).unwrap(), None);
+    }
 }
@@ -1,173 +1,827 @@
+use std::time::{Duration, Instant};
+
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::api::{GenericResponse, Requests};
+use crate::api::market_data::venues::VenueData;
+use crate::api::trading::account::Sorting;
+use crate::api::trading::positions::PositionQuery;
+use crate::api::{endpoints, string_enum, GenericResponse, Mode, PaginationResponse, Response};
+#[cfg(feature = "async")]
+use crate::client::AsyncTradingClient;
 use crate::client::TradingClient;
-use crate::{api::Response, error::Error};
+use crate::error::Error;
+use crate::identifiers::{Isin, Mic};
+use crate::money::{Amount, Price};
+use crate::request::Request;
+use crate::risk::{self, RiskInputs};
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+use crate::transport::Transport;
 
-#[derive(Serialize, Deserialize, Debug)]
-// The struct for placing an order - body of the post request
-pub struct OrderPlacing {
-    pub isin: String,
-    pub expires_at: Option<String>,
+/// How many days in the future an order may expire at the latest
+pub const MAX_EXPIRY_DAYS: i64 = 30;
+
+/// An order to place - body of the post request
+///
+/// Create it with [`OrderRequest::buy`] or [`OrderRequest::sell`], and set the optional
+/// fields with the fluent setters. [`post_order`](crate::client::TradingClient::post_order)
+/// validates the order before sending it.
+///
+/// ```
+/// use septoria::api::orders::OrderRequest;
+///
+/// let isin = "US0378331005".parse().unwrap();
+/// let order = OrderRequest::buy(isin, 2)
+///     .limit("150.50".parse().unwrap())
+///     .venue("XMUN".parse().unwrap())
+///     .notes("first share");
+/// assert!(order.validate().is_ok());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderRequest {
+    /// ISIN of the instrument you want to trade
+    pub isin: Isin,
+    /// Expiry date of the order. The API expires orders after
+    /// [`MAX_EXPIRY_DAYS`] by default.
+    pub expires_at: Option<NaiveDate>,
+    /// "buy" or "sell"
     pub side: OrderType,
-    // "buy" or "sell"
+    /// The amount of shares you want to buy or sell
     pub quantity: i64,
-    pub venue: Option<String>,
+    /// MIC of the venue the order should be placed at
+    pub venue: Option<Mic>,
+    /// Price at which the order becomes a market or limit order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_price: Option<Price>,
+    /// Worst price per share the order may be executed at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_price: Option<Price>,
+    /// Your own notes about the order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Your own unique idempotency key to prevent placing the same order twice.
+    /// Orders with a key are retried on transient failures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl OrderRequest {
+    /// An order of `quantity` shares of `isin` on the given side
+    pub fn new(side: OrderType, isin: Isin, quantity: i64) -> Self {
+        Self {
+            isin,
+            expires_at: None,
+            side,
+            quantity,
+            venue: None,
+            stop_price: None,
+            limit_price: None,
+            notes: None,
+            idempotency: None,
+        }
+    }
+
+    /// An order to buy `quantity` shares of `isin`
+    pub fn buy(isin: Isin, quantity: i64) -> Self {
+        Self::new(OrderType::Buy, isin, quantity)
+    }
+
+    /// An order to sell `quantity` shares of `isin`
+    pub fn sell(isin: Isin, quantity: i64) -> Self {
+        Self::new(OrderType::Sell, isin, quantity)
+    }
+
+    /// Only execute the order at `price` per share or better
+    pub fn limit(mut self, price: Price) -> Self {
+        self.limit_price = Some(price);
+        self
+    }
+
+    /// Only activate the order once the price reaches `price`
+    pub fn stop(mut self, price: Price) -> Self {
+        self.stop_price = Some(price);
+        self
+    }
+
+    /// Let the order expire at the end of `date`
+    pub fn expires(mut self, date: NaiveDate) -> Self {
+        self.expires_at = Some(date);
+        self
+    }
+
+    /// Place the order at `venue`, and keep it alive for its next `trading_days` sessions.
+    ///
+    /// The expiry date is the end of the [`ExpiryWindow`] of the venue, so it is never
+    /// before the venue opens again. Fails if the venue has no upcoming opening days.
+    ///
+    /// [`ExpiryWindow`]: crate::api::market_data::venues::ExpiryWindow
+    pub fn expires_after_sessions(
+        mut self,
+        venue: &VenueData,
+        trading_days: usize,
+    ) -> Result<Self, Error> {
+        let window = venue
+            .expiry_window(trading_days)?
+            .ok_or_else(|| Error::Str(format!("venue {} does not open again", venue.mic)))?;
+        self.expires_at = Some(window.latest);
+        self.venue = Some(venue.mic.clone());
+        Ok(self)
+    }
+
+    /// Place the order at the venue with the given MIC
+    pub fn venue(mut self, mic: Mic) -> Self {
+        self.venue = Some(mic);
+        self
+    }
+
+    /// Attach your own notes to the order
+    pub fn notes(mut self, notes: impl Into<String>) -> Self {
+        self.notes = Some(notes.into());
+        self
+    }
+
+    /// Set the idempotency key, which also makes the order safe to retry
+    pub fn idempotency(mut self, key: impl Into<String>) -> Self {
+        self.idempotency = Some(key.into());
+        self
+    }
+
+    /// How the order is executed, given its stop and limit prices
+    pub fn kind(&self) -> OrderKind {
+        match (self.stop_price, self.limit_price) {
+            (None, None) => OrderKind::Market,
+            (Some(_), None) => OrderKind::Stop,
+            (None, Some(_)) => OrderKind::Limit,
+            (Some(_), Some(_)) => OrderKind::StopLimit,
+        }
+    }
+
+    /// Check the order for mistakes the API would reject it for
+    pub fn validate(&self) -> Result<(), InvalidOrder> {
+        self.validate_at(Utc::now().date_naive())
+    }
+
+    /// Check the order as of the given day
+    pub(crate) fn validate_at(&self, today: NaiveDate) -> Result<(), InvalidOrder> {
+        if self.quantity <= 0 {
+            return Err(InvalidOrder::Quantity(self.quantity));
+        }
+        for price in [self.stop_price, self.limit_price].into_iter().flatten() {
+            if price <= Price::ZERO {
+                return Err(InvalidOrder::Price(price));
+            }
+        }
+        if let (Some(stop), Some(limit)) = (self.stop_price, self.limit_price) {
+            let consistent = match self.side {
+                OrderType::Buy => limit >= stop,
+                OrderType::Sell => limit <= stop,
+            };
+            if !consistent {
+                return Err(InvalidOrder::StopLimit { stop, limit });
+            }
+        }
+        if let Some(expires_at) = self.expires_at {
+            let latest = today + chrono::Duration::days(MAX_EXPIRY_DAYS);
+            if expires_at > latest {
+                return Err(InvalidOrder::Expiry { expires_at, latest });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reasons an [`OrderRequest`] is rejected before it is sent
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidOrder {
+    /// The quantity is zero or negative
+    #[error("the quantity of an order must be positive, got {0}")]
+    Quantity(i64),
+    /// A stop or limit price is zero or negative
+    #[error("stop and limit prices must be positive, got {0}")]
+    Price(Price),
+    /// The limit price would prevent the order from executing once the stop price is reached:
+    /// below the stop price for a buy order, or above it for a sell order
+    #[error("limit price {limit} is on the wrong side of stop price {stop}")]
+    StopLimit {
+        /// The stop price of the order
+        stop: Price,
+        /// The limit price of the order
+        limit: Price,
+    },
+    /// The order expires later than the API allows
+    #[error("orders can expire on {latest} at the latest, got {expires_at}")]
+    Expiry {
+        /// The expiry date of the order
+        expires_at: NaiveDate,
+        /// The latest allowed expiry date
+        latest: NaiveDate,
+    },
+}
+
+/// The side of an order
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderType {
+    /// Buy order
     Buy,
+    /// Sell order
     Sell,
 }
 
+string_enum! {
+    /// The status of an order
+    OrderStatus {
+        /// The order is placed, but has to be activated
+        Inactive => "inactive",
+        /// The order is activated, but not yet routed to the venue
+        Activated => "activated",
+        /// The order is open at the venue
+        Open => "open",
+        /// The order is being executed
+        InProgress => "in_progress",
+        /// The order is being canceled
+        Canceling => "canceling",
+        /// The order was canceled
+        Canceled => "canceled",
+        /// The order was executed
+        Executed => "executed",
+        /// The order expired before it was executed
+        Expired => "expired",
+        /// The order was rejected by the venue
+        Rejected => "rejected",
+    }
+}
+
+impl OrderStatus {
+    /// Whether the order is finished and its status will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Canceled
+                | OrderStatus::Executed
+                | OrderStatus::Expired
+                | OrderStatus::Rejected
+        )
+    }
+
+    /// Whether an order can move from this status to `next`.
+    ///
+    /// Staying in the same status is always legal. Unknown statuses can move anywhere, and
+    /// be reached from any status that is not terminal.
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (Canceled | Executed | Expired | Rejected, _) => false,
+            (Other(_), _) | (_, Other(_)) => true,
+            (Inactive, Activated | Canceling | Canceled | Expired | Rejected) => true,
+            (
+                Activated,
+                Open | InProgress | Canceling | Canceled | Executed | Expired | Rejected,
+            ) => true,
+            (Open, InProgress | Canceling | Canceled | Executed | Expired | Rejected) => true,
+            (InProgress, Canceling | Canceled | Executed | Expired | Rejected) => true,
+            (Canceling, Canceled | Executed) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether an order can move from this status to `next` in one or more transitions
+    fn can_reach(&self, next: &OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        let known = [
+            Inactive, Activated, Open, InProgress, Canceling, Canceled, Executed, Expired, Rejected,
+        ];
+        let mut reached = vec![self.clone()];
+        let mut i = 0;
+        while let Some(status) = reached.get(i).cloned() {
+            if status.can_transition_to(next) {
+                return true;
+            }
+            for candidate in &known {
+                if !reached.contains(candidate) && status.can_transition_to(candidate) {
+                    reached.push(candidate.clone());
+                }
+            }
+            i += 1;
+        }
+        false
+    }
+}
+
+string_enum! {
+    /// How an order is executed, derived from its stop and limit prices
+    OrderKind {
+        /// Executed at the best available price
+        Market => "market",
+        /// Becomes a market order once the stop price is reached
+        Stop => "stop",
+        /// Executed at the limit price or better
+        Limit => "limit",
+        /// Becomes a limit order once the stop price is reached
+        StopLimit => "stop_limit",
+    }
+}
+
+/// Filters for [`list_orders`](crate::client::TradingClient::list_orders)
+///
+/// All filters are optional, and combined with the fluent setters:
+///
+/// ```
+/// use septoria::api::orders::{OrderQuery, OrderStatus, OrderType};
+///
+/// let query = OrderQuery::new()
+///     .side(OrderType::Buy)
+///     .status(OrderStatus::Executed)
+///     .limit(50);
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OrderQuery {
+    /// Only orders created at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    /// Only orders created at or before this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    /// Only orders of this instrument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isin: Option<Isin>,
+    /// Only buy or only sell orders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<OrderType>,
+    /// Only orders with this status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<OrderStatus>,
+    /// Only orders of this kind
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<OrderKind>,
+    /// Only orders placed with the API key of this key creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_creation_id: Option<String>,
+    /// Orders per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The page to fetch, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Order of the results by creation time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sorting: Option<Sorting>,
+}
+
+impl OrderQuery {
+    /// A query without filters, for all orders
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only orders created at or after `from`
+    pub fn from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only orders created at or before `to`
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Only orders of the instrument `isin`
+    pub fn isin(mut self, isin: Isin) -> Self {
+        self.isin = Some(isin);
+        self
+    }
+
+    /// Only buy or only sell orders
+    pub fn side(mut self, side: OrderType) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    /// Only orders with the given status
+    pub fn status(mut self, status: OrderStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Only orders of the given kind
+    pub fn kind(mut self, kind: OrderKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only orders placed with an API key of the given key creation
+    pub fn key_creation_id(mut self, id: impl Into<String>) -> Self {
+        self.key_creation_id = Some(id.into());
+        self
+    }
+
+    /// Fetch `limit` orders per page
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch the given page, starting at 1
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Sort the orders by creation time
+    pub fn sorting(mut self, sorting: Sorting) -> Self {
+        self.sorting = Some(sorting);
+        self
+    }
+}
+
+/// The struct for placing an order - the response of the request
 #[derive(Serialize, Deserialize, Debug)]
-// The struct for placing an order - the response of the request
 pub struct OrderPlacingResponse<T> {
+    /// Timestamp of your request
     pub time: String,
+    /// Status of the request
     pub status: String,
-    pub mode: String,
+    /// Environment the request was placed in: "paper" or "money"
+    pub mode: Mode,
+    /// The actual results of the query
     pub results: Option<T>,
 }
 
+/// The struct for placing an order - the results response of the request
 #[derive(Serialize, Deserialize, Debug)]
-// The struct for placing an order - the results response of the request
 pub struct OrderResults {
     /// Timestamp for when you created your order
     pub created_at: DateTime<Utc>,
     /// Unique Identification number for your order
     pub id: String,
     /// The status of your order
-    pub status: String,
+    pub status: OrderStatus,
     /// Regulatory information about the order
     pub regulatory_information: Option<RegulatoryInformation>,
     /// Where the order was placed
-    pub isin: Option<String>,
+    pub isin: Option<Isin>,
     /// Expiry date of the order
     pub expires_at: Option<String>,
     /// The type of order you placed (buy or sell)
     pub side: Option<OrderType>,
+    /// How the order is executed, e.g. as a limit order
+    #[serde(rename = "type")]
+    pub kind: Option<OrderKind>,
     /// The quantity of the order you placed
     pub quantity: Option<i64>,
     /// The stop_price of the order you placed
-    pub stop_price: Option<String>,
+    pub stop_price: Option<Price>,
     /// The limit_price of the order you placed
-    pub limit_price: Option<String>,
+    pub limit_price: Option<Price>,
     /// The venue of the order you placed
-    pub venue: Option<String>,
-    /// The estimated price of the order you placed
-    pub estimated_price: Option<i64>,
+    pub venue: Option<Mic>,
+    /// The estimated total price of the order you placed
+    pub estimated_price: Option<Amount>,
     /// Notes about your order
     pub notes: Option<String>,
     /// Idempotency for the request
     pub idempotency: Option<String>,
     /// Charges for the order request
-    pub charge: Option<i64>,
-    // Charges for the order request
+    pub charge: Option<Amount>,
+    /// Timestamp at which the charge for the order request is applied
     pub chargeable_at: Option<String>,
     /// Key creation identifier for the order request
     pub key_creation_id: Option<String>,
 }
 
+/// Regulatory information about an order
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RegulatoryInformation {
-    pub costs_entry: Option<i64>,
+    /// Costs for entering the position
+    pub costs_entry: Option<Amount>,
+    /// Costs for entering the position, in percent
     pub costs_entry_pct: Option<String>,
-    pub costs_running: Option<i64>,
+    /// Running costs of the position
+    pub costs_running: Option<Amount>,
+    /// Running costs of the position, in percent
     pub costs_running_pct: Option<String>,
-    pub costs_product: Option<i64>,
+    /// Costs of the product
+    pub costs_product: Option<Amount>,
+    /// Costs of the product, in percent
     pub costs_product_pct: Option<String>,
-    pub costs_exit: Option<i64>,
+    /// Costs for exiting the position
+    pub costs_exit: Option<Amount>,
+    /// Costs for exiting the position, in percent
     pub costs_exit_pct: Option<String>,
-    pub yield_reduction_year: Option<i64>,
+    /// Yield reduction in the first year
+    pub yield_reduction_year: Option<Amount>,
+    /// Yield reduction in the first year, in percent
     pub yield_reduction_year_pct: Option<String>,
-    pub yield_reduction_year_following: Option<i64>,
+    /// Yield reduction in the following years
+    pub yield_reduction_year_following: Option<Amount>,
+    /// Yield reduction in the following years, in percent
     pub yield_reduction_year_following_pct: Option<String>,
-    pub yield_reduction_year_exit: Option<i64>,
+    /// Yield reduction in the year of exit
+    pub yield_reduction_year_exit: Option<Amount>,
+    /// Yield reduction in the year of exit, in percent
     pub yield_reduction_year_exit_pct: Option<String>,
+    /// Estimated holding duration of the position, in years
     pub estimated_holding_duration_years: Option<String>,
-    pub estimated_yield_reduction_total: Option<i64>,
+    /// Estimated total yield reduction
+    pub estimated_yield_reduction_total: Option<Amount>,
+    /// Estimated total yield reduction, in percent
     pub estimated_yield_reduction_total_pct: Option<String>,
+    /// Key investor information document
     #[serde(rename = "KIID")]
     pub kiid: Option<String>,
+    /// Legal disclaimer for the order
     pub legal_disclaimer: Option<String>,
 }
 
+/// Body of the request to activate an order
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ActivateOrder {
+    /// Unique Identification number of the order
     pub id: String,
+    /// Your PIN
     pub pin: i64,
 }
 
-impl TradingClient {
-    /// Get orders
-    pub fn get_order(&self, id: Option<String>) -> Result<GenericResponse<OrderResults>, Error> {
-        let url = format!("{}/orders/{}", self.base_url, id.unwrap_or_default());
-        let response = self.client.get(&url).send()?;
-        let body = response.text()?;
-        let order = serde_json::from_str(&body)?;
-        Ok(order)
-    }
+endpoints! {
+    TradingClient;
 
-    /// Post and create a new order.
-    pub fn post_order(&self, _body: OrderPlacing) -> Result<GenericResponse<OrderResults>, Error> {
+    /// Get a page of your orders, filtered by `query`
+    pub fn list_orders(&self, query: OrderQuery) -> PaginationResponse<OrderResults> {
         const PATH: &str = "orders/";
-        let resp = self.post::<GenericResponse<OrderResults>, _>(PATH, _body);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        Request::get(PATH).query(&query)?
+    }
+
+    /// Get a single order by id
+    pub fn get_order(&self, order_id: &str) -> GenericResponse<OrderResults> {
+        Request::get(format!("orders/{order_id}"))
     }
 
     /// Activate an order by id
-    pub fn activate_order(&self, pin: i64, order_id: &str) -> Result<Response, Error> {
-        let url = format!("orders/{order_id}/activate");
-        let resp = self.post::<Response, ActivateOrder>(
-            url.as_str(),
-            ActivateOrder {
-                id: order_id.to_string(),
-                pin,
+    pub fn activate_order(&self, pin: i64, order_id: &str) -> Response {
+        let body = ActivateOrder {
+            id: order_id.to_string(),
+            pin,
+        };
+        Request::post(format!("orders/{order_id}/activate"), &body)?
+    }
+
+    /// Delete an order by id
+    pub fn delete_order(&self, order_id: &str) -> Response {
+        Request::delete(format!("orders/{order_id}/"))
+    }
+}
+
+/// The request that places an order. Only orders with an idempotency key are retried
+fn post_order_request(
+    body: &OrderRequest,
+) -> Result<Request<GenericResponse<OrderResults>>, Error> {
+    const PATH: &str = "orders/";
+    Ok(Request::post(PATH, body)?.idempotent(body.idempotency.is_some()))
+}
+
+/// The first item of a page, if there is one
+fn first<T>(page: PaginationResponse<T>) -> Option<T> {
+    page.results.unwrap_or_default().into_iter().next()
+}
+
+/// Tracks the status of an order while waiting for it to finish
+struct OrderWait<'a> {
+    /// Id of the order that is waited for
+    order_id: &'a str,
+    /// When to give up
+    deadline: Instant,
+    /// Time between two polls
+    poll_interval: Duration,
+    /// The last status that was seen
+    status: Option<OrderStatus>,
+}
+
+impl<'a> OrderWait<'a> {
+    /// Start waiting for the order with the given id
+    fn new(order_id: &'a str, timeout: Duration, poll_interval: Duration) -> Self {
+        Self {
+            order_id,
+            deadline: Instant::now() + timeout,
+            poll_interval,
+            status: None,
+        }
+    }
+
+    /// Record a polled order, calling `on_change` if its status changed.
+    /// Returns the order once it reached a terminal status, and fails if the order cannot
+    /// have moved from the last status to the new one, even through statuses the polls missed.
+    fn observe(
+        &mut self,
+        response: GenericResponse<OrderResults>,
+        on_change: &mut impl FnMut(&OrderResults),
+    ) -> Result<Option<OrderResults>, Error> {
+        let order = response
+            .results
+            .ok_or_else(|| Error::Str(format!("no order in the response for {}", self.order_id)))?;
+        if let Some(status) = &self.status {
+            if !status.can_reach(&order.status) {
+                return Err(Error::InvalidOrderTransition {
+                    order_id: self.order_id.to_string(),
+                    from: status.clone(),
+                    to: order.status,
+                });
+            }
+        }
+        if self.status.as_ref() != Some(&order.status) {
+            self.status = Some(order.status.clone());
+            on_change(&order);
+        }
+        Ok(order.status.is_terminal().then_some(order))
+    }
+
+    /// How long to wait before the next poll, or an error if the deadline has passed
+    fn next_delay(&self) -> Result<Duration, Error> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::OrderTimeout {
+                order_id: self.order_id.to_string(),
+                status: self.status.clone().unwrap_or(OrderStatus::Inactive),
+            });
+        }
+        Ok(remaining.min(self.poll_interval))
+    }
+}
+
+impl<Tr: Transport> TradingClient<Tr> {
+    /// Post and create a new order.
+    ///
+    /// The order is validated first, and fails with [`Error::InvalidOrder`] without being sent
+    /// if it is invalid. With a [`RiskGuard`](crate::risk::RiskGuard), it then has to pass
+    /// [`check_order`](Self::check_order). It is only retried on transient failures if it
+    /// carries an idempotency key.
+    pub fn post_order(&self, body: OrderRequest) -> Result<GenericResponse<OrderResults>, Error> {
+        self.check_order(&body)?;
+        self.send(post_order_request(&body)?)
+    }
+
+    /// Validate an order and run it through the risk guard of the client, without placing it.
+    ///
+    /// Fails with [`Error::InvalidOrder`] or [`Error::RiskCheck`]. Without a risk guard, only
+    /// the validation runs.
+    pub fn check_order(&self, order: &OrderRequest) -> Result<(), Error> {
+        order.validate()?;
+        let Some(guard) = &self.risk_guard else {
+            return Ok(());
+        };
+        let data_url = guard.parsed_data_url()?;
+        let positions = match order.side {
+            OrderType::Buy => vec![],
+            OrderType::Sell => self
+                .paginate(self.get_positions(PositionQuery::new().isin(order.isin.clone()))?)
+                .collect_all(usize::MAX)?,
+        };
+        let inputs = RiskInputs {
+            account: self.get_account_information()?.results,
+            positions,
+            instrument: first(self.send_to(&data_url, risk::instrument_request(order)?)?),
+            venue: first(self.send_to(&data_url, risk::venue_request(order)?)?),
+            quote: match risk::needs_quote(order) {
+                true => first(self.send_to(&data_url, risk::quote_request(order)?)?),
+                false => None,
             },
-        );
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
+            today: Utc::now().date_naive(),
+        };
+        guard.check(order, &inputs)
+    }
+
+    /// Poll an order every `poll_interval` until it is canceled, executed, expired or
+    /// rejected, and return it in that final state.
+    ///
+    /// `on_change` is called with the order whenever its status changes, starting with the
+    /// status of the first poll. Fails with [`Error::OrderTimeout`] if the order is not
+    /// finished within `timeout`, and with [`Error::InvalidOrderTransition`] if its status
+    /// changes in a way [`OrderStatus::can_transition_to`] does not allow, even through
+    /// statuses between two polls.
+    pub fn wait_for_order(
+        &self,
+        order_id: &str,
+        timeout: Duration,
+        poll_interval: Duration,
+        mut on_change: impl FnMut(&OrderResults),
+    ) -> Result<OrderResults, Error> {
+        let mut wait = OrderWait::new(order_id, timeout, poll_interval);
+        loop {
+            if let Some(order) = wait.observe(self.get_order(order_id)?, &mut on_change)? {
+                return Ok(order);
+            }
+            std::thread::sleep(wait.next_delay()?);
         }
     }
+}
 
-    /// Delete an order by id
-    pub fn delete_order(&self, order_id: &str) -> Result<Response, Error> {
-        const PATH: &str = "orders/{order_id}/";
-        let resp = self.delete::<Response>(PATH, order_id);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncTradingClient<Tr> {
+    /// Post and create a new order.
+    ///
+    /// The order is validated first, and fails with [`Error::InvalidOrder`] without being sent
+    /// if it is invalid. With a [`RiskGuard`](crate::risk::RiskGuard), it then has to pass
+    /// [`check_order`](Self::check_order). It is only retried on transient failures if it
+    /// carries an idempotency key.
+    pub async fn post_order(
+        &self,
+        body: OrderRequest,
+    ) -> Result<GenericResponse<OrderResults>, Error> {
+        self.check_order(&body).await?;
+        self.send(post_order_request(&body)?).await
+    }
+
+    /// Validate an order and run it through the risk guard of the client, without placing it.
+    ///
+    /// Fails with [`Error::InvalidOrder`] or [`Error::RiskCheck`]. Without a risk guard, only
+    /// the validation runs.
+    pub async fn check_order(&self, order: &OrderRequest) -> Result<(), Error> {
+        order.validate()?;
+        let Some(guard) = &self.risk_guard else {
+            return Ok(());
+        };
+        let data_url = guard.parsed_data_url()?;
+        let positions = match order.side {
+            OrderType::Buy => vec![],
+            OrderType::Sell => {
+                let query = PositionQuery::new().isin(order.isin.clone());
+                let first_page = self.get_positions(query).await?;
+                self.collect_all(first_page, usize::MAX).await?
+            }
+        };
+        let inputs = RiskInputs {
+            account: self.get_account_information().await?.results,
+            positions,
+            instrument: first(
+                self.send_to(&data_url, risk::instrument_request(order)?)
+                    .await?,
+            ),
+            venue: first(self.send_to(&data_url, risk::venue_request(order)?).await?),
+            quote: match risk::needs_quote(order) {
+                true => first(self.send_to(&data_url, risk::quote_request(order)?).await?),
+                false => None,
+            },
+            today: Utc::now().date_naive(),
+        };
+        guard.check(order, &inputs)
+    }
+
+    /// Poll an order every `poll_interval` until it is canceled, executed, expired or
+    /// rejected, and return it in that final state.
+    ///
+    /// `on_change` is called with the order whenever its status changes, starting with the
+    /// status of the first poll. Fails with [`Error::OrderTimeout`] if the order is not
+    /// finished within `timeout`, and with [`Error::InvalidOrderTransition`] if its status
+    /// changes in a way [`OrderStatus::can_transition_to`] does not allow, even through
+    /// statuses between two polls.
+    pub async fn wait_for_order(
+        &self,
+        order_id: &str,
+        timeout: Duration,
+        poll_interval: Duration,
+        mut on_change: impl FnMut(&OrderResults),
+    ) -> Result<OrderResults, Error> {
+        let mut wait = OrderWait::new(order_id, timeout, poll_interval);
+        loop {
+            if let Some(order) = wait.observe(self.get_order(order_id).await?, &mut on_change)? {
+                return Ok(order);
+            }
+            tokio::time::sleep(wait.next_delay()?).await;
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use chrono::prelude::*;
-    use std::env;
 
-    use crate::*;
+    use crate::cassette::{fixture, ReplayTransport};
+    use crate::client::TradingClient;
+    use crate::error::Error;
+    use crate::mock::MockServer;
+    use crate::money::{Amount, Price};
+
+    use super::{
+        InvalidOrder, OrderKind, OrderQuery, OrderRequest, OrderStatus, OrderWait, MAX_EXPIRY_DAYS,
+    };
+    use crate::identifiers::Isin;
 
     #[test]
     fn test_placing_and_activating_an_order() {
-        dotenv::dotenv().unwrap();
-        let local: DateTime<Local> = Local::now();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = client::TradingClient::paper_client(&api_key);
-        let body = super::OrderPlacing {
-            isin: "US0378331005".to_string(),
-            expires_at: Some(local.format("%Y-%m-%d").to_string()),
-            side: super::OrderType::Buy,
-            quantity: 1,
-            venue: Some("XMUN".to_string()),
-        };
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let venues = server.data_client().get_venues().unwrap().results.unwrap();
+        let body = OrderRequest::buy("US0378331005".parse().unwrap(), 1)
+            .expires_after_sessions(&venues[0], 2)
+            .unwrap();
         let resp = client.post_order(body).unwrap();
         assert_eq!(resp.status, "ok");
         let resp = client
@@ -175,4 +829,200 @@ mod test {
             .unwrap();
         assert_eq!(resp.status, "ok");
     }
+
+    #[test]
+    fn test_order_payloads_from_cassette() {
+        let replay = ReplayTransport::load(fixture("orders.json")).unwrap();
+        let client = TradingClient::builder("key")
+            .build_with_transport(replay)
+            .unwrap();
+        let body = OrderRequest::buy("US0378331005".parse().unwrap(), 1)
+            .expires(NaiveDate::from_ymd_opt(2021, 11, 22).unwrap())
+            .venue("XMUN".parse().unwrap())
+            .idempotency("1234abcd");
+        let order = client.post_order(body).unwrap().results.unwrap();
+        assert_eq!(order.status, OrderStatus::Inactive);
+        assert_eq!(order.estimated_price, Amount::from_euros(147));
+        assert!(order.regulatory_information.is_some());
+        let resp = client.activate_order(1234, &order.id).unwrap();
+        assert_eq!(resp.status, "ok");
+    }
+
+    #[test]
+    fn test_order_status_falls_back_to_other() {
+        let status: OrderStatus = serde_json::from_str(r#""in_progress""#).unwrap();
+        assert_eq!(status, OrderStatus::InProgress);
+        let status: OrderStatus = serde_json::from_str(r#""brand_new_status""#).unwrap();
+        assert_eq!(status, OrderStatus::Other("brand_new_status".to_string()));
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#""brand_new_status""#
+        );
+    }
+
+    #[test]
+    fn test_order_request_validation() {
+        let today = NaiveDate::from_ymd_opt(2022, 3, 1).unwrap();
+        let order = || OrderRequest::sell("US0378331005".parse().unwrap(), 3);
+        let price = |euros| Price::from_euros(euros).unwrap();
+
+        assert_eq!(
+            order().stop(price(100)).limit(price(95)).validate_at(today),
+            Ok(())
+        );
+        assert_eq!(
+            order()
+                .stop(price(100))
+                .limit(price(105))
+                .validate_at(today),
+            Err(InvalidOrder::StopLimit {
+                stop: price(100),
+                limit: price(105),
+            })
+        );
+        assert_eq!(
+            OrderRequest::buy("US0378331005".parse().unwrap(), 0).validate_at(today),
+            Err(InvalidOrder::Quantity(0))
+        );
+        assert_eq!(
+            order().limit(Price::ZERO).validate_at(today),
+            Err(InvalidOrder::Price(Price::ZERO))
+        );
+        let latest = today + chrono::Duration::days(MAX_EXPIRY_DAYS);
+        assert!(order().expires(latest).validate_at(today).is_ok());
+        assert!(matches!(
+            order()
+                .expires(latest.succ_opt().unwrap())
+                .validate_at(today),
+            Err(InvalidOrder::Expiry { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_orders_are_not_sent() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let order = OrderRequest::buy("US0378331005".parse().unwrap(), -1);
+        let error = client.post_order(order).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidOrder(InvalidOrder::Quantity(-1))
+        ));
+        assert!(server.state().orders.is_empty());
+    }
+
+    #[test]
+    fn test_listing_and_filtering_orders() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let apple: Isin = "US0378331005".parse().unwrap();
+        let sap: Isin = "DE0007164600".parse().unwrap();
+        let placed = client
+            .post_order(OrderRequest::buy(apple.clone(), 1))
+            .unwrap()
+            .results
+            .unwrap();
+        client.activate_order(1234, &placed.id).unwrap();
+        client
+            .post_order(OrderRequest::buy(sap, 2).limit(Price::from_euros(100).unwrap()))
+            .unwrap();
+
+        let orders = client.list_orders(OrderQuery::new()).unwrap();
+        assert_eq!(orders.total, 2);
+        let executed = client
+            .list_orders(OrderQuery::new().status(OrderStatus::Executed))
+            .unwrap();
+        let executed = executed.results.unwrap();
+        assert_eq!(executed.len(), 1);
+        assert_eq!(executed[0].isin.as_ref(), Some(&apple));
+        let limits = client
+            .list_orders(OrderQuery::new().kind(OrderKind::Limit).limit(1))
+            .unwrap();
+        assert_eq!(limits.total, 1);
+
+        let order = client.get_order(&placed.id).unwrap().results.unwrap();
+        assert_eq!(order.status, OrderStatus::Executed);
+    }
+
+    #[test]
+    fn test_order_status_transitions() {
+        assert!(OrderStatus::Inactive.can_transition_to(&OrderStatus::Activated));
+        assert!(OrderStatus::Open.can_transition_to(&OrderStatus::Executed));
+        assert!(OrderStatus::Canceling.can_transition_to(&OrderStatus::Canceled));
+        assert!(!OrderStatus::Executed.can_transition_to(&OrderStatus::Open));
+        assert!(!OrderStatus::Activated.can_transition_to(&OrderStatus::Inactive));
+        assert!(!OrderStatus::Inactive.can_transition_to(&OrderStatus::Executed));
+        assert!(OrderStatus::Inactive.can_reach(&OrderStatus::Executed));
+        assert!(!OrderStatus::Canceling.can_reach(&OrderStatus::Open));
+        assert!(OrderStatus::Expired.is_terminal());
+        assert!(!OrderStatus::InProgress.is_terminal());
+    }
+
+    #[test]
+    fn test_wait_for_order() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let order = OrderRequest::buy("US0378331005".parse().unwrap(), 1);
+        let id = client.post_order(order).unwrap().results.unwrap().id;
+        let wait = |client: &TradingClient, changes: &mut Vec<OrderStatus>| {
+            client.wait_for_order(
+                &id,
+                Duration::from_millis(50),
+                Duration::from_millis(10),
+                |order| changes.push(order.status.clone()),
+            )
+        };
+
+        let mut changes = vec![];
+        let error = wait(&client, &mut changes).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::OrderTimeout {
+                status: OrderStatus::Inactive,
+                ..
+            }
+        ));
+        assert_eq!(changes, [OrderStatus::Inactive]);
+
+        client.activate_order(1234, &id).unwrap();
+        let mut changes = vec![];
+        let order = wait(&client, &mut changes).unwrap();
+        assert_eq!(order.status, OrderStatus::Executed);
+        assert_eq!(changes, [OrderStatus::Executed]);
+    }
+
+    #[test]
+    fn test_wait_rejects_illegal_transitions() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let order = OrderRequest::buy("US0378331005".parse().unwrap(), 1);
+        let id = client.post_order(order).unwrap().results.unwrap().id;
+        let mut wait = OrderWait::new(&id, Duration::from_secs(1), Duration::from_millis(10));
+
+        let observe = |wait: &mut OrderWait, status: OrderStatus| {
+            server.state().orders[0].status = status;
+            wait.observe(client.get_order(&id).unwrap(), &mut |_| {})
+        };
+
+        // Statuses between two polls are not seen
+        assert!(observe(&mut wait, OrderStatus::Inactive).unwrap().is_none());
+        assert!(observe(&mut wait, OrderStatus::InProgress)
+            .unwrap()
+            .is_none());
+
+        // A canceling order cannot be filled again
+        let mut wait = OrderWait::new(&id, Duration::from_secs(1), Duration::from_millis(10));
+        assert!(observe(&mut wait, OrderStatus::Canceling)
+            .unwrap()
+            .is_none());
+        let error = observe(&mut wait, OrderStatus::Open).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidOrderTransition {
+                from: OrderStatus::Canceling,
+                to: OrderStatus::Open,
+                ..
+            }
+        ));
+    }
 }
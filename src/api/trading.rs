@@ -1,2 +1,4 @@
-mod account;
-mod positions;
+/// Module for interacting with the account related endpoints
+pub mod account;
+/// Module for interacting with the position related endpoints
+pub mod positions;
@@ -4,22 +4,29 @@ use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
 
-use crate::api::Requests;
+use crate::api::{endpoints, string_enum, Mode, Response};
+#[cfg(feature = "async")]
+use crate::client::AsyncTradingClient;
 use crate::client::TradingClient;
 use crate::error::Error;
+use crate::money::Amount;
+use crate::request::Request;
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+use crate::transport::Transport;
 
-mod documents;
+/// Module for interacting with the bank statement and document endpoints
+pub mod documents;
 /// Module for interacting with the withdrawal related endpoints
 pub mod withdrawals;
 
-///
+/// Response wrapper of the account endpoint
 #[derive(Deserialize, Debug)]
 pub struct AccountInformation<T> {
     /// Timestamp of your API request
     pub time: String,
     /// Environment the request was placed in: "paper" or "money"
-    // TODO: Make this an enum
-    pub mode: String,
+    pub mode: Mode,
     /// API returns "ok" when account was successfully retrieved.
     pub status: String,
     /// The actual results of the query. Depends upon the given generics
@@ -44,6 +51,8 @@ pub struct AccountResults {
     pub phone: Option<String>,
     /// Your specified address
     pub address: Option<String>,
+    /// Country of your address, as ISO 3166-1 alpha-2 code, e.g. "DE"
+    pub address_country: Option<String>,
     /// The billing address you provided for your account
     pub billing_address: Option<String>,
     /// The billing email adress you provided for your account
@@ -53,8 +62,7 @@ pub struct AccountResults {
     /// The billing VAT number you provided for your account
     pub billing_vat: Option<String>,
     /// The mode your account is currently on - "paper" or "money"
-    // TODO: Make this an enum
-    pub mode: String,
+    pub mode: Mode,
     /// Identification Number of your securities account
     pub deposit_id: Option<String>,
     /// The internal client identification number related to your account
@@ -72,66 +80,78 @@ pub struct AccountResults {
     /// The balance calculates as follows:
     /// (End-of-day balance from the day before) + (amount_sold_intraday)
     /// - (amount_bought_intraday) - (amount_open_withdrawals).
-    pub balance: i64,
+    pub balance: Amount,
     /// This number shows you how much cash you have left to invest.
     ///
     /// The cash_to_invest calculates as follows:
     ///
     /// (balance) - (amount_open_orders)
-    pub cash_to_invest: i64,
+    pub cash_to_invest: Amount,
     /// This number shows you how much cash you have in your account to withdraw to your reference account.
     ///
     /// cash_to_withdraw is calculated as follows:
     ///
     /// (Your end-of-day balance from the day before) - (amount_bought_intraday)
     /// - (amount_open_withdrawals) - (amount_open_orders).
-    pub cash_to_withdraw: i64,
+    pub cash_to_withdraw: Amount,
     /// This is the intraday buy order amount.
     /// For example, if you bought 2 shares for 100€ each that day, the amount_bought_intraday
     /// would be 200€ (the API would return 2000000 in that case,
     /// see the [numbers page](https://docs.lemon.markets/numbers) for more information).
-    pub amount_bought_intraday: i64,
+    pub amount_bought_intraday: Amount,
     /// This is the intraday sell order amount.
     /// For example, if you sold 3 shares for 50€ each that day,
     /// the amount_sold_intraday would be 150€ (the API would return 1500000 in that case,
     /// see the [numbers page](https://docs.lemon.markets/numbers) for more information).
-    pub amount_sold_intraday: i64,
+    pub amount_sold_intraday: Amount,
     /// This is the intraday amount of open orders.
     // If you place an order that has amount X and its status is open,
     // the amount_open_orders would be the order amount
     // (+ the sum of all other open intraday orders).
-    pub amount_open_orders: i64,
+    pub amount_open_orders: Amount,
     /// For example, if you withdraw 500€ to your reference account,
     /// amount_open_withdrawals would return 5000000
     /// (see the [numbers page](https://docs.lemon.markets/numbers) for more information
     /// on the numbers format in the Trading API).
-    pub amount_open_withdrawals: i64,
+    pub amount_open_withdrawals: Amount,
     /// This is the amount of estimated taxes (25%) for your intraday sell orders.
     /// For example, if you made a profit of 100€ from your intraday sell orders,
     /// the API would return 250000
     /// (see the [numbers page](https://docs.lemon.markets/numbers
     /// for more information in the Trading API
-    pub amount_estimate_taxes: i64,
+    pub amount_estimate_taxes: Amount,
     /// Timestamp of live trading account approval
     pub approved_at: Option<DateTime<Utc>>,
     /// We offer different subscription plans for trading with lemon.markets.
     /// This endpoint tells you which plan you are currently on - go, investor, trader, or b2b.
-    // TODO: Make this an enum
-    pub trading_plan: String,
+    pub trading_plan: Plan,
     /// We offer different subscription plans for trading with lemon.markets.
     /// This endpoint tells you which plan you are currently on - go, investor, trader, or b2b.
-    // TODO: Make this an enum
-    pub data_plan: String,
+    pub data_plan: Plan,
     /// Your tax tax allowance - between 0 and 801 €, as specified in your onboarding process
-    pub tax_allowance: Option<i64>,
+    pub tax_allowance: Option<Amount>,
     /// Relevant start date for your tax allowance (usually 01/01/ of respective year)
     pub tax_allowance_start: Option<DateTime<Utc>>,
     /// Relevant end date for your tax allowance (usually 31/12/ of respective year)
     pub tax_allowance_end: Option<DateTime<Utc>>,
 }
 
+string_enum! {
+    /// Subscription plan of an account, for trading or for market data
+    Plan {
+        /// The free plan
+        Go => "go",
+        /// The plan for investors
+        Investor => "investor",
+        /// The plan for active traders
+        Trader => "trader",
+        /// The plan for businesses
+        B2b => "b2b",
+    }
+}
+
 /// Enum for the different ways of sorting the results
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Sorting {
     /// Use asc_ to sort your bank statements in ascending order (oldest ones first),
     #[serde(rename = "asc_")]
@@ -150,30 +170,182 @@ impl fmt::Display for Sorting {
     }
 }
 
-impl TradingClient {
+/// Changes to the settings of your account, for
+/// [`update_account`](crate::client::TradingClient::update_account)
+///
+/// Only the fields that are set are sent, all others keep their current value.
+///
+/// ```
+/// use septoria::api::trading::account::AccountUpdate;
+///
+/// let update = AccountUpdate::new()
+///     .billing_name("Lemon Capital GmbH")
+///     .billing_email("billing@example.com");
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AccountUpdate {
+    /// The billing address for your account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_address: Option<String>,
+    /// The billing email address for your account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_email: Option<String>,
+    /// The billing name for your account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_name: Option<String>,
+    /// The billing VAT number for your account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_vat: Option<String>,
+    /// Country of your address, as ISO 3166-1 alpha-2 code, e.g. "DE"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_country: Option<String>,
+}
+
+impl AccountUpdate {
+    /// An update that changes nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change the billing address
+    pub fn billing_address(mut self, billing_address: impl Into<String>) -> Self {
+        self.billing_address = Some(billing_address.into());
+        self
+    }
+
+    /// Change the billing email address
+    pub fn billing_email(mut self, billing_email: impl Into<String>) -> Self {
+        self.billing_email = Some(billing_email.into());
+        self
+    }
+
+    /// Change the billing name
+    pub fn billing_name(mut self, billing_name: impl Into<String>) -> Self {
+        self.billing_name = Some(billing_name.into());
+        self
+    }
+
+    /// Change the billing VAT number
+    pub fn billing_vat(mut self, billing_vat: impl Into<String>) -> Self {
+        self.billing_vat = Some(billing_vat.into());
+        self
+    }
+
+    /// Change the country of your address
+    pub fn address_country(mut self, address_country: impl Into<String>) -> Self {
+        self.address_country = Some(address_country.into());
+        self
+    }
+}
+
+endpoints! {
+    TradingClient;
+
     /// Get account information
-    pub fn get_account_information(&self) -> Result<AccountInformation<AccountResults>, Error> {
+    pub fn get_account_information(&self) -> AccountInformation<AccountResults> {
         const PATH: &str = "account";
-        let resp = self.get::<AccountInformation<AccountResults>>(PATH);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        Request::get(PATH)
+    }
+}
+
+/// The request that changes the settings of the account
+fn update_account_request(update: &AccountUpdate) -> Result<Request<Response>, Error> {
+    const PATH: &str = "account";
+    Request::put(PATH, update)
+}
+
+impl<Tr: Transport> TradingClient<Tr> {
+    /// Change the settings of your account, and return the account with the changes applied.
+    ///
+    /// The API rejects changes it does not allow with
+    /// [`ErrorCode::ForbiddenInCurrentState`](crate::error::ErrorCode::ForbiddenInCurrentState),
+    /// e.g. while the account is being onboarded, or with
+    /// [`ErrorCode::PlanNotAllowed`](crate::error::ErrorCode::PlanNotAllowed) if your plan
+    /// does not include them.
+    pub fn update_account(&self, update: AccountUpdate) -> Result<AccountResults, Error> {
+        self.send(update_account_request(&update)?)?;
+        Ok(self.get_account_information()?.results)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncTradingClient<Tr> {
+    /// Change the settings of your account, and return the account with the changes applied.
+    ///
+    /// The API rejects changes it does not allow with
+    /// [`ErrorCode::ForbiddenInCurrentState`](crate::error::ErrorCode::ForbiddenInCurrentState),
+    /// e.g. while the account is being onboarded, or with
+    /// [`ErrorCode::PlanNotAllowed`](crate::error::ErrorCode::PlanNotAllowed) if your plan
+    /// does not include them.
+    pub async fn update_account(&self, update: AccountUpdate) -> Result<AccountResults, Error> {
+        self.send(update_account_request(&update)?).await?;
+        Ok(self.get_account_information().await?.results)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::env;
+    use reqwest::Method;
 
+    use super::AccountUpdate;
+    use crate::cassette::{fixture, ReplayTransport};
     use crate::client::TradingClient;
+    use crate::error::ErrorCode;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_account_information() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
         let resp = client.get_account_information().unwrap();
         assert_eq!(resp.status, "ok");
     }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_get_account_information_async() {
+        let server = MockServer::start().unwrap();
+        let client = crate::client::AsyncTradingClient::new("key".to_string(), server.url());
+        let resp = client.get_account_information().await.unwrap();
+        assert_eq!(resp.status, "ok");
+    }
+
+    #[test]
+    fn test_update_account() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let update = AccountUpdate::new()
+            .billing_name("Lemon Capital GmbH")
+            .address_country("DE");
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            serde_json::json!({ "billing_name": "Lemon Capital GmbH", "address_country": "DE" })
+        );
+
+        let account = client.update_account(update).unwrap();
+        assert_eq!(account.billing_name.as_deref(), Some("Lemon Capital GmbH"));
+        assert_eq!(account.address_country.as_deref(), Some("DE"));
+        assert_eq!(account.billing_email, None);
+
+        server.fail_next(Method::PUT, "account", ErrorCode::PlanNotAllowed);
+        let error = client
+            .update_account(AccountUpdate::new().billing_vat("DE123456789"))
+            .unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::PlanNotAllowed));
+        assert_eq!(server.state().account.billing_vat, None);
+    }
+
+    #[test]
+    fn test_account_payload_from_cassette() {
+        let replay = ReplayTransport::load(fixture("account.json")).unwrap();
+        let client = TradingClient::builder("key")
+            .build_with_transport(replay)
+            .unwrap();
+        let account = client.get_account_information().unwrap();
+        assert_eq!(
+            account.results.account_id,
+            "acc_pyNJdkkmmJ2F6X9m6gkHT2jMJrV6dsfGS3"
+        );
+        assert_eq!(account.results.cash_to_invest.to_string(), "€448.00");
+    }
 }
@@ -1,113 +1,540 @@
-// TODO: Implement the code below. For now it's just commented out
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
 
-/*
-#[derive(Deserialize, Serialize, Debug)]
-pub struct BankStatementResponse {
+use chrono::prelude::*;
+#[cfg(feature = "async")]
+use futures_util::StreamExt;
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "async")]
+use tokio::io::{AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use crate::api::trading::account::Sorting;
+use crate::api::{endpoints, string_enum, PaginationResponse};
+#[cfg(feature = "async")]
+use crate::client::AsyncTradingClient;
+use crate::client::TradingClient;
+use crate::error::Error;
+use crate::identifiers::Isin;
+use crate::money::Amount;
+use crate::request::Request;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncTransport, BodyStream};
+use crate::transport::{BodyReader, HttpResponse, Transport};
+
+/// A booking on the cash account, e.g. a pay-in or a dividend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BankStatement {
+    /// Unique Identification number of the bank statement
     pub id: String,
+    /// The account the statement was booked on
     pub account_id: String,
+    /// The type of the bank statement
     #[serde(rename = "type")]
-    pub bankstatementtypes: BankStatementTypes,
-    pub date: String,
-    pub amount: i64,
-    pub isin: String,
-    pub isin_title: String,
-    pub created_at: String,
-    pub quantity: i64,
-}
-
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum BankStatementTypes {
-    PayIn,
-    PayOut,
-    OrderBuy,
-    OrderSell,
-    EodBalance,
-    Dividend,
-    TaxRefund,
-}
-
-
-
-// Porobably the easiest way  to implement Display.
-// TODO: This is a bit of a hack, but it might work for now.
-impl fmt::Display for BankStatementTypes {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match to_variant_name(self) {
-            Ok(name) => write!(f, "{}", name),
-            _ => write!(f, ""),
-        }
+    pub statement_type: BankStatementType,
+    /// The day the statement was booked
+    pub date: NaiveDate,
+    /// Amount of the bank statement
+    pub amount: Amount,
+    /// ISIN of the instrument, for orders and dividends
+    pub isin: Option<Isin>,
+    /// Title of the instrument, for orders and dividends
+    pub isin_title: Option<String>,
+    /// Timestamp at which the statement was created
+    pub created_at: DateTime<Utc>,
+    /// The amount of shares, for orders
+    pub quantity: Option<i64>,
+}
+
+string_enum! {
+    /// The different types of bank statements
+    BankStatementType {
+        /// Money was transferred to the account
+        PayIn => "pay_in",
+        /// Money was withdrawn to the reference account
+        PayOut => "pay_out",
+        /// A buy order was paid
+        OrderBuy => "order_buy",
+        /// A sell order was paid out
+        OrderSell => "order_sell",
+        /// The balance at the end of a day
+        EodBalance => "eod_balance",
+        /// A dividend was paid out
+        Dividend => "dividend",
+        /// Taxes were refunded
+        TaxRefund => "tax_refund",
+    }
+}
+
+/// Filters for [`get_bank_statements`](crate::client::TradingClient::get_bank_statements)
+///
+/// ```
+/// use septoria::api::trading::account::documents::{BankStatementQuery, BankStatementType};
+///
+/// let query = BankStatementQuery::new()
+///     .statement_type(BankStatementType::Dividend)
+///     .from("2022-01-01".parse().unwrap());
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct BankStatementQuery {
+    /// Only statements of this type
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub statement_type: Option<BankStatementType>,
+    /// Only statements booked on or after this day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDate>,
+    /// Only statements booked on or before this day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<NaiveDate>,
+    /// Order of the results by date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sorting: Option<Sorting>,
+    /// Statements per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The page to fetch, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
+
+impl BankStatementQuery {
+    /// A query without filters, for all bank statements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only statements of the given type
+    pub fn statement_type(mut self, statement_type: BankStatementType) -> Self {
+        self.statement_type = Some(statement_type);
+        self
+    }
+
+    /// Only statements booked on or after `from`
+    pub fn from(mut self, from: NaiveDate) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only statements booked on or before `to`
+    pub fn to(mut self, to: NaiveDate) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Sort the statements by date
+    pub fn sorting(mut self, sorting: Sorting) -> Self {
+        self.sorting = Some(sorting);
+        self
+    }
+
+    /// Fetch `limit` statements per page
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch the given page, starting at 1
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+}
+
+endpoints! {
+    TradingClient;
+
+    /// Get a page of the bank statements of your account, filtered by `query`
+    pub fn get_bank_statements(&self, query: BankStatementQuery) -> PaginationResponse<BankStatement> {
+        const PATH: &str = "account/bankstatements";
+        Request::get(PATH).query(&query)?
+    }
+}
+
+/// A document of your account, e.g. a monthly report or a tax report
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Document {
+    /// Unique Identification number of the document
+    pub id: String,
+    /// File name of the document
+    pub name: String,
+    /// Timestamp at which the document was created
+    pub created_at: DateTime<Utc>,
+    /// What the document is about
+    pub category: DocumentCategory,
+    /// When you first downloaded the document, if you did
+    pub viewed_first_at: Option<DateTime<Utc>>,
+    /// When you last downloaded the document, if you did
+    pub viewed_last_at: Option<DateTime<Utc>>,
+}
+
+string_enum! {
+    /// The different categories of documents
+    DocumentCategory {
+        /// The overview of a month
+        MonthlyReport => "monthly_report",
+        /// The yearly report for your tax return
+        TaxReport => "tax_report",
+        /// The confirmation of an executed order
+        TradeConfirmation => "trade_confirmation",
+        /// Information about the costs of an order
+        CostInformation => "cost_information",
+        /// Contracts and terms of your account
+        Contract => "contract",
+    }
+}
+
+endpoints! {
+    TradingClient;
+
+    /// Get a page of the documents of your account. Use `paginate` to get the following pages
+    pub fn list_documents(&self) -> PaginationResponse<Document> {
+        Request::get("account/documents")
+    }
+}
+
+/// The request for the content of a document, starting at byte `offset`
+fn download_request(document_id: &str, offset: u64) -> Result<Request<()>, Error> {
+    let request = Request::get(format!("account/documents/{}", document_id));
+    if offset == 0 {
+        return Ok(request);
+    }
+    let range = HeaderValue::from_str(&format!("bytes={}-", offset))
+        .map_err(|e| Error::Str(format!("invalid range: {}", e)))?;
+    Ok(request.header(RANGE, range))
+}
+
+/// A header of the response as text, if it is present
+fn header<'a, B>(response: &'a HttpResponse<B>, name: &HeaderName) -> Option<&'a str> {
+    response.headers.get(name)?.to_str().ok()
+}
+
+/// The first byte of the document in the response
+fn range_start<B>(response: &HttpResponse<B>) -> Result<u64, Error> {
+    if response.status != StatusCode::PARTIAL_CONTENT {
+        return Ok(0);
+    }
+    // `bytes <start>-<end>/<size>`
+    header(response, &CONTENT_RANGE)
+        .and_then(|range| range.strip_prefix("bytes "))
+        .and_then(|range| range.split('-').next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| Error::Str("partial download without a valid Content-Range".to_string()))
+}
+
+/// The size of the whole document as the server announced it, if it did
+fn announced_size<B>(response: &HttpResponse<B>, start: u64) -> Option<u64> {
+    match header(response, &CONTENT_RANGE) {
+        Some(range) => range.rsplit('/').next()?.parse().ok(),
+        None => Some(start + header(response, &CONTENT_LENGTH)?.parse::<u64>().ok()?),
+    }
+}
+
+/// Check a download ended with the announced size, and return the size
+fn check_size(document_id: &str, expected: Option<u64>, received: u64) -> Result<u64, Error> {
+    match expected {
+        Some(expected) if expected != received => Err(Error::IncompleteDownload {
+            id: document_id.to_string(),
+            expected,
+            received,
+        }),
+        _ => Ok(received),
     }
 }
-impl Client{
-    /// Get bank statements
-    pub fn get_bank_statements(
+
+/// Whether the server refused to resume a download, because the stored part is already as
+/// long as the whole document
+fn cannot_resume<B>(offset: u64, response: &Result<HttpResponse<B>, Error>) -> bool {
+    matches!(response, Err(e) if offset > 0 && e.status() == Some(StatusCode::RANGE_NOT_SATISFIABLE))
+}
+
+/// The byte the response to a download that started at byte `offset` starts at
+fn resumed_at<B>(document_id: &str, offset: u64, response: &HttpResponse<B>) -> Result<u64, Error> {
+    // Servers that ignore the range send the whole document again
+    let start = range_start(response)?;
+    if start > offset {
+        return Err(Error::Str(format!(
+            "download of {} resumed at byte {} instead of {}",
+            document_id, start, offset
+        )));
+    }
+    Ok(start)
+}
+
+/// The file to store a download that starts at byte `start` in, truncated to `start` bytes
+fn resume_file(path: &Path, start: u64) -> Result<File, Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    file.set_len(start)?;
+    file.seek(SeekFrom::Start(start))?;
+    Ok(file)
+}
+
+/// Copy the body of a download that starts at byte `start` to `writer`, and check its size
+fn copy_document(
+    document_id: &str,
+    start: u64,
+    mut response: HttpResponse<BodyReader>,
+    mut writer: impl Write,
+) -> Result<u64, Error> {
+    let received = io::copy(&mut response.body, &mut writer)?;
+    writer.flush()?;
+    check_size(
+        document_id,
+        announced_size(&response, start),
+        start + received,
+    )
+}
+
+/// The async counterpart of [`copy_document`]
+#[cfg(feature = "async")]
+async fn copy_document_async(
+    document_id: &str,
+    start: u64,
+    mut response: HttpResponse<BodyStream>,
+    mut writer: impl AsyncWrite + Unpin,
+) -> Result<u64, Error> {
+    let mut received = 0;
+    while let Some(chunk) = response.body.next().await {
+        let chunk = chunk?;
+        writer.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+    }
+    writer.flush().await?;
+    check_size(
+        document_id,
+        announced_size(&response, start),
+        start + received,
+    )
+}
+
+impl<Tr: Transport> TradingClient<Tr> {
+    /// Download the content of a document, usually a PDF, into `writer`.
+    ///
+    /// The content is written as it arrives, without holding the whole document in memory.
+    /// Returns the number of bytes written. Fails with [`Error::IncompleteDownload`] if the
+    /// download is shorter or longer than the server announced.
+    pub fn download_document(&self, document_id: &str, writer: impl Write) -> Result<u64, Error> {
+        let response = self.send_streaming(download_request(document_id, 0)?)?;
+        copy_document(document_id, 0, response, writer)
+    }
+
+    /// Download the content of a document into the file at `path`.
+    ///
+    /// If the file already exists, it is taken to be the start of the document from an
+    /// interrupted download, and only the rest is fetched. A file that is as long as the
+    /// document or longer is downloaded again. Returns the size of the file, and fails with
+    /// [`Error::IncompleteDownload`] if it is not the size the server announced.
+    pub fn download_document_to(
         &self,
-        statement_type: Option<BankStatementTypes>,
-        from: Option<String>,
-        to: Option<String>,
-        sorting: Option<Sorting>,
-        limit: Option<i32>,
-    ) -> Result<PaginationResponse<BankStatementResponse>, Error> {
-        let path = "account/bankstatements/";
-        let mut query = vec![];
-
-        if let Some(statement_type) = statement_type {
-            if let Ok(query_string) =
-                serde_urlencoded::to_string(&vec![("type", statement_type.to_string())])
-            {
-                query.push(query_string);
-            }
-        }
-        if let Some(from) = from {
-            if let Ok(query_string) = serde_urlencoded::to_string(&vec![("from", from)]) {
-                query.push(query_string);
-            }
-        }
-        if let Some(to) = to {
-            if let Ok(query_string) = serde_urlencoded::to_string(&vec![("to", to)]) {
-                query.push(query_string);
-            }
-        }
-        if let Some(sorting) = sorting {
-            if let Ok(query_string) =
-                serde_urlencoded::to_string(&vec![("sorting", sorting.to_string())])
-            {
-                query.push(query_string);
-            }
+        document_id: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<u64, Error> {
+        let path = path.as_ref();
+        let mut offset = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let mut response = self.send_streaming(download_request(document_id, offset)?);
+        if cannot_resume(offset, &response) {
+            offset = 0;
+            response = self.send_streaming(download_request(document_id, offset)?);
         }
+        let response = response?;
+        let start = resumed_at(document_id, offset, &response)?;
+        let file = resume_file(path, start)?;
+        copy_document(document_id, start, response, file)
+    }
+}
 
-        if let Some(limit) = limit {
-            if let Ok(query_string) =
-                serde_urlencoded::to_string(&vec![("limit", limit.to_string())])
-            {
-                query.push(query_string);
-            }
-        }
-        let resp = self
-            .get_with_query::<PaginationResponse<BankStatementResponse>, Vec<String>>(path, query);
-        match resp {
-            Ok(r) => Ok(r),
-            // Err(e) => Err(e),
-            Err(e) => Err(Error::Str(e.to_string())),
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncTradingClient<Tr> {
+    /// Download the content of a document, usually a PDF, into `writer`.
+    ///
+    /// The content is written as it arrives, without holding the whole document in memory.
+    /// Returns the number of bytes written. Fails with [`Error::IncompleteDownload`] if the
+    /// download is shorter or longer than the server announced.
+    pub async fn download_document(
+        &self,
+        document_id: &str,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<u64, Error> {
+        let response = self
+            .send_streaming(download_request(document_id, 0)?)
+            .await?;
+        copy_document_async(document_id, 0, response, writer).await
+    }
+
+    /// Download the content of a document into the file at `path`.
+    ///
+    /// If the file already exists, it is taken to be the start of the document from an
+    /// interrupted download, and only the rest is fetched. A file that is as long as the
+    /// document or longer is downloaded again. Returns the size of the file, and fails with
+    /// [`Error::IncompleteDownload`] if it is not the size the server announced.
+    pub async fn download_document_to(
+        &self,
+        document_id: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<u64, Error> {
+        let path = path.as_ref();
+        let mut offset = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let mut response = self
+            .send_streaming(download_request(document_id, offset)?)
+            .await;
+        if cannot_resume(offset, &response) {
+            offset = 0;
+            response = self
+                .send_streaming(download_request(document_id, offset)?)
+                .await;
         }
+        let response = response?;
+        let start = resumed_at(document_id, offset, &response)?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await?;
+        file.set_len(start).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        copy_document_async(document_id, start, response, file).await
     }
 }
 
 #[cfg(test)]
-mod document_tests{
-    use std::env;
+mod tests {
+    use super::{BankStatementQuery, BankStatementType, DocumentCategory};
+    use crate::api::orders::OrderRequest;
+    use crate::api::trading::account::Sorting;
+    use crate::error::Error;
+    use crate::mock::MockServer;
+    use crate::money::Amount;
 
-    use super::*;
-    use crate::*;
     #[test]
     fn test_get_bank_statements() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = client::Client::paper_client(&api_key);
-        let resp = client
-            .get_bank_statements(None, None, None, None, None)
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let order = OrderRequest::buy("DE0007164600".parse().unwrap(), 2);
+        let id = client.post_order(order).unwrap().results.unwrap().id;
+        client.activate_order(1234, &id).unwrap();
+
+        let statements = client
+            .get_bank_statements(BankStatementQuery::new().sorting(Sorting::Desc))
+            .unwrap()
+            .results
+            .unwrap();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].statement_type, BankStatementType::OrderBuy);
+        assert_eq!(statements[0].amount, -Amount::from_euros(240).unwrap());
+        assert_eq!(statements[0].quantity, Some(2));
+        let total: Amount = statements.iter().map(|statement| statement.amount).sum();
+        assert_eq!(total, server.state().account.balance);
+
+        let pay_ins = client
+            .get_bank_statements(BankStatementQuery::new().statement_type(BankStatementType::PayIn))
             .unwrap();
+        assert_eq!(pay_ins.total, 1);
+    }
+
+    #[test]
+    fn test_list_and_download_documents() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let documents = client.list_documents().unwrap().results.unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].category, DocumentCategory::Contract);
+
+        let mut pdf = vec![];
+        let size = client
+            .download_document(&documents[0].id, &mut pdf)
+            .unwrap();
+        assert_eq!(size, pdf.len() as u64);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert_eq!(pdf, server.state().document_contents[&documents[0].id]);
+
+        let error = client.download_document("doc_unknown", vec![]).unwrap_err();
+        assert!(error.code().is_some_and(|code| code.is_not_found()));
+    }
+
+    #[test]
+    fn test_download_document_resumes() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let content = server.state().document_contents["doc_mock000000"].clone();
+        let path = std::env::temp_dir().join(format!(
+            "septoria-document-{}-{}.pdf",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+
+        // An interrupted download left the first bytes behind
+        std::fs::write(&path, &content[..10]).unwrap();
+        let size = client
+            .download_document_to("doc_mock000000", &path)
+            .unwrap();
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+
+        // A file that is longer than the document is replaced
+        std::fs::write(&path, [content.as_slice(), b"garbage"].concat()).unwrap();
+        let size = client
+            .download_document_to("doc_mock000000", &path)
+            .unwrap();
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_download_document_async() {
+        let server = MockServer::start().unwrap();
+        let client = crate::client::AsyncTradingClient::new("key".to_string(), server.url());
+        let content = server.state().document_contents["doc_mock000000"].clone();
+
+        let mut pdf = vec![];
+        let size = client
+            .download_document("doc_mock000000", &mut pdf)
+            .await
+            .unwrap();
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(pdf, content);
+
+        let path = std::env::temp_dir().join(format!(
+            "septoria-document-{}-{}.pdf",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        tokio::fs::write(&path, &content[..10]).await.unwrap();
+        let size = client
+            .download_document_to("doc_mock000000", &path)
+            .await
+            .unwrap();
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), content);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[test]
+    fn test_check_size() {
+        assert_eq!(super::check_size("doc", Some(10), 10).unwrap(), 10);
+        assert_eq!(super::check_size("doc", None, 7).unwrap(), 7);
+        assert!(matches!(
+            super::check_size("doc", Some(10), 7),
+            Err(Error::IncompleteDownload {
+                expected: 10,
+                received: 7,
+                ..
+            })
+        ));
     }
 }
-*/
@@ -1,95 +1,372 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{Requests, Response};
+use crate::api::{endpoints, GenericResponse, PaginationResponse, Response};
+#[cfg(feature = "async")]
+use crate::client::AsyncTradingClient;
 use crate::client::TradingClient;
-use crate::error::Error;
+use crate::error::{Error, ErrorCode};
+use crate::money::Amount;
+use crate::request::Request;
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+use crate::transport::Transport;
 use chrono::prelude::*;
 
 /// Struct for the Withdrawal Request
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// Build it with [`WithdrawalRequest::builder`]. Every request carries an idempotency key, so
+/// sending the same request again, e.g. after a timeout, cannot pay out twice.
+///
+/// ```
+/// use septoria::api::trading::account::withdrawals::WithdrawalRequest;
+/// use septoria::money::Amount;
+///
+/// let withdrawal = WithdrawalRequest::builder(Amount::from_euros(100).unwrap())
+///     .pin(1234)
+///     .build()
+///     .unwrap();
+/// // Store the key to retry the same withdrawal after a restart
+/// let key = withdrawal.idempotency().to_string();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WithdrawalRequest {
     /// Amount to withdraw
-    amount: usize,
+    amount: Amount,
     /// PIN to use for withdrawal
     pin: i64,
     /// You can set your own unique idempotency key to prevent duplicate operations.
     /// Subsequent requests with the same idempotency key will then not go through and throw an error message.
     /// This means you cannot make the same withdrawal twice.
+    idempotency: String,
+}
+
+impl WithdrawalRequest {
+    /// Start building a withdrawal of `amount`
+    pub fn builder(amount: Amount) -> WithdrawalRequestBuilder {
+        WithdrawalRequestBuilder {
+            amount,
+            pin: None,
+            idempotency: None,
+        }
+    }
+
+    /// Amount to withdraw
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
+    /// The idempotency key of the withdrawal. Keep it to send the same withdrawal again
+    pub fn idempotency(&self) -> &str {
+        &self.idempotency
+    }
+
+    /// Check the account has at least `cash_to_withdraw` to cover the withdrawal
+    fn check_funds(&self, cash_to_withdraw: Amount) -> Result<(), WithdrawalError> {
+        match self.amount > cash_to_withdraw {
+            true => Err(WithdrawalError::InsufficientFunds {
+                amount: self.amount,
+                available: Some(cash_to_withdraw),
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Builder for a [`WithdrawalRequest`]
+#[derive(Debug, Clone)]
+pub struct WithdrawalRequestBuilder {
+    /// Amount to withdraw
+    amount: Amount,
+    /// PIN to use for withdrawal
+    pin: Option<i64>,
+    /// Idempotency key, generated if none is set
     idempotency: Option<String>,
 }
 
-///
-#[derive(Deserialize, Serialize, Debug)]
+impl WithdrawalRequestBuilder {
+    /// Set the PIN you created during onboarding
+    pub fn pin(mut self, pin: i64) -> Self {
+        self.pin = Some(pin);
+        self
+    }
+
+    /// Use your own idempotency key, e.g. one you stored for an earlier attempt of the same
+    /// withdrawal, instead of a new one
+    pub fn idempotency(mut self, key: impl Into<String>) -> Self {
+        self.idempotency = Some(key.into());
+        self
+    }
+
+    /// Build the withdrawal. Fails if the amount is not positive or the PIN is missing
+    pub fn build(self) -> Result<WithdrawalRequest, WithdrawalError> {
+        if self.amount <= Amount::ZERO {
+            return Err(WithdrawalError::Amount(self.amount));
+        }
+        let pin = self.pin.ok_or(WithdrawalError::PinMissing)?;
+        Ok(WithdrawalRequest {
+            amount: self.amount,
+            pin,
+            idempotency: self.idempotency.unwrap_or_else(new_idempotency_key),
+        })
+    }
+}
+
+/// A random idempotency key
+fn new_idempotency_key() -> String {
+    format!("wtd-{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..))
+}
+
+/// Reasons a withdrawal is rejected, before it is sent or by the API
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WithdrawalError {
+    /// The amount is zero or negative
+    #[error("the amount of a withdrawal must be positive, got {0}")]
+    Amount(Amount),
+    /// The account does not have enough cash that can be withdrawn
+    #[error(
+        "cannot withdraw {amount}{}",
+        .available.map(|available| format!(", only {} are available", available)).unwrap_or_default()
+    )]
+    InsufficientFunds {
+        /// Amount of the withdrawal
+        amount: Amount,
+        /// `cash_to_withdraw` of the account, `None` if the API rejected the withdrawal
+        available: Option<Amount>,
+    },
+    /// No PIN was given
+    #[error("a PIN is required to withdraw")]
+    PinMissing,
+    /// The PIN is wrong
+    #[error("the PIN is invalid")]
+    PinInvalid,
+    /// The withdrawal exceeds the amount you may withdraw
+    #[error("the withdrawal limit is exceeded")]
+    LimitExceeded,
+    /// Too many withdrawals were requested
+    #[error("too many withdrawals were requested")]
+    RequestLimitExceeded,
+}
+
+impl WithdrawalError {
+    /// The withdrawal error for an error code the API answered `withdrawal` with, if there is one
+    fn from_code(code: &ErrorCode, withdrawal: &WithdrawalRequest) -> Option<Self> {
+        match code {
+            ErrorCode::WithdrawInsufficientFunds => Some(Self::InsufficientFunds {
+                amount: withdrawal.amount,
+                available: None,
+            }),
+            ErrorCode::PinMissing => Some(Self::PinMissing),
+            ErrorCode::PinInvalid => Some(Self::PinInvalid),
+            ErrorCode::WithdrawLimitExceeded => Some(Self::LimitExceeded),
+            ErrorCode::WithdrawRequestLimitExceeded => Some(Self::RequestLimitExceeded),
+            _ => None,
+        }
+    }
+}
+
+/// Turn the errors of the API that concern `withdrawal` into [`Error::Withdrawal`]
+fn withdrawal_error(withdrawal: &WithdrawalRequest, error: Error) -> Error {
+    match error
+        .code()
+        .and_then(|code| WithdrawalError::from_code(code, withdrawal))
+    {
+        Some(withdrawal_error) => withdrawal_error.into(),
+        None => error,
+    }
+}
+
+/// Path of the withdrawals of the account, for listing and submitting them
+const WITHDRAWALS_PATH: &str = "account/withdrawals";
+
+/// The request that submits a withdrawal. It is safe to retry thanks to its idempotency key
+fn post_withdrawal_request(withdrawal: &WithdrawalRequest) -> Result<Request<Response>, Error> {
+    Ok(Request::post(WITHDRAWALS_PATH, withdrawal)?.idempotent(true))
+}
+
+/// A withdrawal from your brokerage account to your reference account
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Withdrawal {
     /// A unique Identification Number of your withdrawal
     pub id: String,
     /// The amount of the withdrawal
-    pub amount: i64,
+    pub amount: Amount,
     /// Timestamp at which you created the withdrawal
     pub created_at: DateTime<Utc>,
-    /// Timestamp at which the withdrawal was processed by our partner bank
-    pub date: DateTime<Utc>,
+    /// Timestamp at which the withdrawal was processed by our partner bank.
+    /// `None` while the withdrawal is pending
+    pub date: Option<DateTime<Utc>>,
     /// Your own unique idempotency key that you specified in your POST request to prevent
     /// duplicate withdrawals.
     pub idempotency: Option<String>,
 }
 
-impl TradingClient {
-    /// Get account withdrawls
-    // TODO: Add support for pagination
-    // TODO: Add docs for params
-    pub fn get_account_withdrawls(
-        &self,
-        _limit: Option<i32>,
-        _page: Option<i32>,
-    ) -> Result<Response, Error> {
-        const PATH: &str = "account/withdrawals";
-        let resp = self.get::<Response>(PATH);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+impl Withdrawal {
+    /// Whether the partner bank has not processed the withdrawal yet. The amounts of pending
+    /// withdrawals make up `amount_open_withdrawals` of the account
+    pub fn is_pending(&self) -> bool {
+        self.date.is_none()
     }
+}
+
+endpoints! {
+    TradingClient;
 
+    /// Get a page of your withdrawals, with `limit` withdrawals per page.
+    /// Use `paginate` to get the following pages
+    pub fn list_withdrawals(&self, limit: Option<i64>, page: Option<u32>) -> PaginationResponse<Withdrawal> {
+        Request::get(WITHDRAWALS_PATH).query(&(("limit", limit), ("page", page)))?
+    }
+
+    /// Get a single withdrawal by its id
+    pub fn get_withdrawal(&self, withdrawal_id: &str) -> GenericResponse<Withdrawal> {
+        Request::get(format!("{}/{}", WITHDRAWALS_PATH, withdrawal_id))
+    }
+}
+
+impl<Tr: Transport> TradingClient<Tr> {
     /// Submit a new withdrawal
-    /// TODO: Add docs for params
-    pub fn post_withdrawal(&self, withdrawal: WithdrawalRequest) -> Result<Response, Error> {
-        const PATH: &str = "account/withdrawals/";
-        let resp = self.post::<Response, WithdrawalRequest>(PATH, withdrawal);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+    ///
+    /// The `cash_to_withdraw` of the account is checked first. Fails with
+    /// [`Error::Withdrawal`] if it does not cover the withdrawal, or if the API rejects the PIN
+    /// or a withdrawal limit. Sending the same request again does not withdraw twice.
+    pub fn post_withdrawal(&self, withdrawal: &WithdrawalRequest) -> Result<Response, Error> {
+        let account = self.get_account_information()?.results;
+        withdrawal.check_funds(account.cash_to_withdraw)?;
+        self.send(post_withdrawal_request(withdrawal)?)
+            .map_err(|error| withdrawal_error(withdrawal, error))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncTradingClient<Tr> {
+    /// Submit a new withdrawal
+    ///
+    /// The `cash_to_withdraw` of the account is checked first. Fails with
+    /// [`Error::Withdrawal`] if it does not cover the withdrawal, or if the API rejects the PIN
+    /// or a withdrawal limit. Sending the same request again does not withdraw twice.
+    pub async fn post_withdrawal(&self, withdrawal: &WithdrawalRequest) -> Result<Response, Error> {
+        let account = self.get_account_information().await?.results;
+        withdrawal.check_funds(account.cash_to_withdraw)?;
+        self.send(post_withdrawal_request(withdrawal)?)
+            .await
+            .map_err(|error| withdrawal_error(withdrawal, error))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use reqwest::Method;
 
     use super::*;
+    use crate::mock::MockServer;
 
     #[test]
-    fn test_get_account_withdrawls() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
-        let resp = client.get_account_withdrawls(None, None).unwrap();
-        assert_eq!(resp.status, "ok");
+    fn test_list_withdrawals() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        for cents in [100, 200, 300] {
+            let withdrawal = WithdrawalRequest::builder(Amount::from_cents(cents).unwrap())
+                .pin(1234)
+                .build()
+                .unwrap();
+            client.post_withdrawal(&withdrawal).unwrap();
+        }
+
+        let first_page = client.list_withdrawals(Some(2), None).unwrap();
+        assert_eq!(first_page.total, 3);
+        let withdrawals = client.paginate(first_page).collect_all(10).unwrap();
+        assert_eq!(withdrawals.len(), 3);
+        let open: Amount = withdrawals
+            .iter()
+            .filter(|withdrawal| withdrawal.is_pending())
+            .map(|withdrawal| withdrawal.amount)
+            .sum();
+        assert_eq!(open, server.state().account.amount_open_withdrawals);
+
+        let withdrawal = client.get_withdrawal(&withdrawals[1].id).unwrap();
+        assert_eq!(withdrawal.results.unwrap().amount, withdrawals[1].amount);
+        let error = client.get_withdrawal("wtd_unknown").unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::WithdrawalNotFound));
     }
 
     #[test]
-    // TODO: Figure out why this often return 400 error
     fn test_post_withdrawal() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
-        let withdrawal = WithdrawalRequest {
-            amount: 100, // 0.01 EUR
-            pin: 1234,
-            idempotency: None,
-        };
-        let resp = client.post_withdrawal(withdrawal).unwrap();
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let withdrawal = WithdrawalRequest::builder(Amount::from_cents(1).unwrap())
+            .pin(1234)
+            .build()
+            .unwrap();
+        let resp = client.post_withdrawal(&withdrawal).unwrap();
         assert_eq!(resp.status, "ok");
+
+        // Sending the same withdrawal again does not pay out twice
+        client.post_withdrawal(&withdrawal).unwrap();
+        assert_eq!(server.state().withdrawals.len(), 1);
+    }
+
+    #[test]
+    fn test_withdrawal_errors() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let amount = Amount::from_euros(10).unwrap();
+        let build = |pin| WithdrawalRequest::builder(amount).pin(pin).build().unwrap();
+
+        assert_eq!(
+            WithdrawalRequest::builder(amount).build().unwrap_err(),
+            WithdrawalError::PinMissing
+        );
+        assert_eq!(
+            WithdrawalRequest::builder(Amount::ZERO)
+                .pin(1234)
+                .build()
+                .unwrap_err(),
+            WithdrawalError::Amount(Amount::ZERO)
+        );
+
+        let too_much = WithdrawalRequest::builder(Amount::from_euros(1_000_000).unwrap())
+            .pin(1234)
+            .build()
+            .unwrap();
+        let error = client.post_withdrawal(&too_much).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Withdrawal(WithdrawalError::InsufficientFunds { .. })
+        ));
+        assert!(error.is_funds_error());
+
+        // The API can reject a withdrawal the account seemed to cover, e.g. after a concurrent one
+        server.fail_next(
+            Method::POST,
+            WITHDRAWALS_PATH,
+            ErrorCode::WithdrawInsufficientFunds,
+        );
+        let error = client.post_withdrawal(&build(1234)).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Withdrawal(WithdrawalError::InsufficientFunds {
+                amount: a,
+                available: None,
+            }) if a == amount
+        ));
+        assert!(error.is_funds_error());
+
+        let error = client.post_withdrawal(&build(4321)).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Withdrawal(WithdrawalError::PinInvalid)
+        ));
+
+        server.fail_next(
+            Method::POST,
+            WITHDRAWALS_PATH,
+            ErrorCode::WithdrawRequestLimitExceeded,
+        );
+        let error = client.post_withdrawal(&build(1234)).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Withdrawal(WithdrawalError::RequestLimitExceeded)
+        ));
+        assert!(server.state().withdrawals.is_empty());
     }
 }
@@ -1,45 +1,118 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{PaginationResponse, Requests};
-use crate::client::TradingClient;
-use crate::error::Error;
+use crate::api::{endpoints, PaginationResponse};
+use crate::identifiers::Isin;
+use crate::money::{Amount, Price};
+use crate::request::Request;
 
-mod performance;
-mod statements;
+/// Module for interacting with the position performance endpoint
+pub mod performance;
+/// Module for interacting with the position statements endpoint
+pub mod statements;
 
+/// A position in your portfolio
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Position {
-    pub isin: String,
+    /// ISIN of the instrument
+    pub isin: Isin,
+    /// Title of the instrument
     pub isin_title: String,
+    /// The amount of shares you hold
     pub quantity: i64,
-    pub buy_price_avg: i64,
-    pub estimated_price_total: i64,
-    pub estimated_price: i64,
+    /// Average buy price of the position
+    pub buy_price_avg: Price,
+    /// Estimated total value of the position
+    pub estimated_price_total: Amount,
+    /// Estimated price of a single share
+    pub estimated_price: Price,
 }
 
-impl TradingClient {
-    /// Get all positions
-    pub fn get_positions(&self) -> Result<PaginationResponse<Position>, Error> {
+/// Filters for [`get_positions`](crate::client::TradingClient::get_positions)
+///
+/// ```
+/// use septoria::api::trading::positions::PositionQuery;
+///
+/// let query = PositionQuery::new().isin("US0378331005".parse().unwrap());
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PositionQuery {
+    /// Only the position in this instrument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isin: Option<Isin>,
+    /// Positions per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The page to fetch, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
+
+impl PositionQuery {
+    /// A query without filters, for all positions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the position in the given instrument
+    pub fn isin(mut self, isin: Isin) -> Self {
+        self.isin = Some(isin);
+        self
+    }
+
+    /// Fetch `limit` positions per page
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch the given page, starting at 1
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+}
+
+endpoints! {
+    TradingClient;
+
+    /// Get a page of your positions, filtered by `query`
+    pub fn get_positions(&self, query: PositionQuery) -> PaginationResponse<Position> {
         const PATH: &str = "positions/";
-        let resp = self.get::<PaginationResponse<Position>>(PATH);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        Request::get(PATH).query(&query)?
     }
 }
 
 #[cfg(test)]
 mod position_tests {
-    use crate::client::TradingClient;
-    use std::env;
+    use super::PositionQuery;
+    use crate::api::orders::OrderRequest;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_positions() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
-        let positions = client.get_positions().unwrap();
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let positions = client.get_positions(PositionQuery::new()).unwrap();
         assert_eq!(positions.status.unwrap(), "ok");
     }
+
+    #[test]
+    fn test_get_positions_by_isin() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        for isin in ["US0378331005", "DE0007164600"] {
+            let order = OrderRequest::buy(isin.parse().unwrap(), 1);
+            let id = client.post_order(order).unwrap().results.unwrap().id;
+            client.activate_order(1234, &id).unwrap();
+        }
+
+        let isin = "DE0007164600".parse().unwrap();
+        let positions = client
+            .get_positions(PositionQuery::new().isin(isin))
+            .unwrap()
+            .results
+            .unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].isin.as_str(), "DE0007164600");
+    }
 }
@@ -1,50 +1,133 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{PaginationResponse, Requests};
-use crate::client::TradingClient;
-use crate::error::Error;
+use crate::api::trading::account::Sorting;
+use crate::api::{endpoints, PaginationResponse};
+use crate::identifiers::Isin;
+use crate::money::Amount;
+use crate::request::Request;
 use chrono::prelude::*;
 
+/// Performance of a single position
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PositionPerformance {
-    pub isin: String,
+    /// ISIN of the instrument
+    pub isin: Isin,
+    /// Title of the instrument
     pub isin_title: String,
-    pub profit: i64,
-    pub loss: i64,
+    /// Profit made with the position
+    pub profit: Amount,
+    /// Loss made with the position
+    pub loss: Amount,
+    /// The amount of shares bought
     pub quantity_bought: i64,
+    /// The amount of shares sold
     pub quantity_sold: i64,
+    /// The amount of shares still held
     pub quantity_open: i64,
+    /// Timestamp at which the position was opened
     pub opened_at: Option<DateTime<Utc>>,
+    /// Timestamp at which the position was closed
     pub closed_at: Option<DateTime<Utc>>,
-    pub fees: i64,
+    /// Fees paid for the position
+    pub fees: Amount,
 }
 type PositionPerformancePagination = PaginationResponse<PositionPerformance>;
 
-impl TradingClient {
-    /// Get an overview of your position performances
+/// Filters for [`get_positions_performance`](crate::client::TradingClient::get_positions_performance)
+///
+/// ```
+/// use septoria::api::trading::positions::performance::PerformanceQuery;
+///
+/// let query = PerformanceQuery::new().from("2022-01-01T00:00:00Z".parse().unwrap());
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PerformanceQuery {
+    /// Only the performance of positions in this instrument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isin: Option<Isin>,
+    /// Only positions opened at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    /// Only positions opened at or before this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    /// Order of the results by opening time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sorting: Option<Sorting>,
+    /// Positions per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The page to fetch, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
+
+impl PerformanceQuery {
+    /// A query without filters, for the performance of all positions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only the performance of positions in the given instrument
+    pub fn isin(mut self, isin: Isin) -> Self {
+        self.isin = Some(isin);
+        self
+    }
+
+    /// Only positions opened at or after `from`
+    pub fn from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only positions opened at or before `to`
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Sort the positions by opening time
+    pub fn sorting(mut self, sorting: Sorting) -> Self {
+        self.sorting = Some(sorting);
+        self
+    }
+
+    /// Fetch `limit` positions per page
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch the given page, starting at 1
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+}
+
+endpoints! {
+    TradingClient;
+
+    /// Get an overview of your position performances, filtered by `query`
     ///  Using this endpoint, you can retrieve when positions were opened and closed,
     /// potential profits/losses, or related fees for position orders.
-    pub fn get_positions_performance(&self) -> Result<PositionPerformancePagination, Error> {
+    pub fn get_positions_performance(&self, query: PerformanceQuery) -> PositionPerformancePagination {
         const PATH: &str = "positions/performance";
-        let resp = self.get::<PositionPerformancePagination>(PATH);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+        Request::get(PATH).query(&query)?
     }
 }
 #[cfg(test)]
 mod tests {
-    use std::env;
-
-    use crate::client::TradingClient;
+    use super::PerformanceQuery;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_positions_performance() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
-        let positions = client.get_positions_performance().unwrap();
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let positions = client
+            .get_positions_performance(PerformanceQuery::new())
+            .unwrap();
         assert_eq!(positions.status.unwrap(), "ok");
     }
 }
@@ -1,71 +1,188 @@
 use serde::{Deserialize, Serialize};
 
-use crate::api::{PaginationResponse, Requests};
-use crate::client::TradingClient;
-use crate::error::Error;
-use crate::query_tuple;
+use crate::api::trading::account::Sorting;
+use crate::api::{endpoints, string_enum, PaginationResponse};
+use crate::identifiers::Isin;
+use crate::request::Request;
 use chrono::prelude::*;
 
+/// A change event that happened to one of your positions
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Statement {
+    /// Unique Identification number of the statement
     pub id: Option<String>,
+    /// The order that caused the statement, if any
     pub order_id: Option<String>,
+    /// External identification number of the statement
     pub external_id: Option<String>,
+    /// The type of the statement
     #[serde(rename = "type")]
-    pub statement_type: String,
+    pub statement_type: StatementType,
+    /// The amount of shares affected
     pub quantity: i64,
-    pub isin: String,
+    /// ISIN of the instrument
+    pub isin: Isin,
+    /// Title of the instrument
     pub isin_title: String,
+    /// The date of the statement
     pub date: NaiveDate,
+    /// Timestamp at which the statement was created
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum StatementType {
-    OrderBuy,
-    OrderSell,
-    Split,
-    Import,
-    Snx,
+string_enum! {
+    /// The different types of position statements
+    StatementType {
+        /// A buy order was executed
+        OrderBuy => "order_buy",
+        /// A sell order was executed
+        OrderSell => "order_sell",
+        /// The instrument was split
+        Split => "split",
+        /// The position was imported
+        Import => "import",
+        /// Shares were booked in or out for a corporate action
+        Snx => "snx",
+    }
 }
 
 type StatementPagination = PaginationResponse<Statement>;
 
-impl TradingClient {
-    /// Get all change events happening to your positions.
-    pub fn get_statements(
-        &self,
-        limit: Option<i64>,
-        page: Option<u32>,
-    ) -> Result<StatementPagination, Error> {
-        const PATH: &str = "positions/statements";
+/// Filters for [`get_statements`](crate::client::TradingClient::get_statements)
+///
+/// ```
+/// use septoria::api::trading::positions::statements::{StatementQuery, StatementType};
+///
+/// let query = StatementQuery::new()
+///     .types([StatementType::OrderBuy, StatementType::OrderSell])
+///     .isin("US0378331005".parse().unwrap());
+/// ```
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StatementQuery {
+    /// Only statements of this instrument
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isin: Option<Isin>,
+    /// Only statements created at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    /// Only statements created at or before this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    /// Only statements of these types. All types if empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<StatementType>,
+    /// Order of the results by creation time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sorting: Option<Sorting>,
+    /// Statements per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// The page to fetch, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+}
 
-        let mut query: Vec<String> = vec![];
-        TradingClient::get_query_string(query_tuple!(limit), &mut query);
-        TradingClient::get_query_string(query_tuple!(page), &mut query);
+impl StatementQuery {
+    /// A query without filters, for all statements
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let resp = self.get_with_query::<StatementPagination, Vec<String>>(PATH, query);
-        match resp {
-            Ok(r) => Ok(r),
-            Err(e) => Err(e),
-        }
+    /// Only statements of the given instrument
+    pub fn isin(mut self, isin: Isin) -> Self {
+        self.isin = Some(isin);
+        self
+    }
+
+    /// Only statements created at or after `from`
+    pub fn from(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only statements created at or before `to`
+    pub fn to(mut self, to: DateTime<Utc>) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Only statements of the given types
+    pub fn types(mut self, types: impl IntoIterator<Item = StatementType>) -> Self {
+        self.types = types.into_iter().collect();
+        self
+    }
+
+    /// Sort the statements by creation time
+    pub fn sorting(mut self, sorting: Sorting) -> Self {
+        self.sorting = Some(sorting);
+        self
+    }
+
+    /// Fetch `limit` statements per page
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch the given page, starting at 1
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+}
+
+endpoints! {
+    TradingClient;
+
+    /// Get a page of the change events of your positions, filtered by `query`
+    pub fn get_statements(&self, query: StatementQuery) -> StatementPagination {
+        const PATH: &str = "positions/statements";
+        Request::get(PATH).query(&query)?
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::env;
-
-    use crate::client::TradingClient;
+    use super::{StatementQuery, StatementType};
+    use crate::api::orders::OrderRequest;
+    use crate::api::trading::account::Sorting;
+    use crate::mock::MockServer;
 
     #[test]
     fn test_get_statement() {
-        dotenv::dotenv().unwrap();
-        let api_key = env::var("LEMON_MARKET_TRADING_API_KEY").unwrap();
-        let client = TradingClient::paper_client(&api_key);
-        let _page = 1;
-        let statements = client.get_statements(None, None).unwrap();
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let statements = client.get_statements(StatementQuery::new()).unwrap();
         assert_eq!(statements.status.unwrap(), "ok");
     }
+
+    #[test]
+    fn test_filter_statements() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        let start = chrono::Utc::now();
+        for order in [
+            OrderRequest::buy("US0378331005".parse().unwrap(), 2),
+            OrderRequest::buy("DE0007164600".parse().unwrap(), 3),
+            OrderRequest::sell("DE0007164600".parse().unwrap(), 1),
+        ] {
+            let id = client.post_order(order).unwrap().results.unwrap().id;
+            client.activate_order(1234, &id).unwrap();
+        }
+
+        let query = StatementQuery::new()
+            .isin("DE0007164600".parse().unwrap())
+            .from(start)
+            .sorting(Sorting::Desc);
+        let statements = client.get_statements(query).unwrap().results.unwrap();
+        let types: Vec<_> = statements.iter().map(|s| &s.statement_type).collect();
+        assert_eq!(types, [&StatementType::OrderSell, &StatementType::OrderBuy]);
+
+        let query = StatementQuery::new().types([StatementType::OrderBuy, StatementType::Split]);
+        let statements = client.get_statements(query).unwrap();
+        assert_eq!(statements.total, 2);
+
+        let query = StatementQuery::new().to(start);
+        assert_eq!(client.get_statements(query).unwrap().total, 0);
+    }
 }
@@ -0,0 +1,21 @@
+//! Serves the offline lemon.markets mock on localhost
+//!
+//! Usage: `septoria-mock [ADDRESS]`, where the address defaults to `127.0.0.1:8080`.
+//! Point a client at the printed base url, with any API key.
+
+use septoria::mock::MockServer;
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let server = match MockServer::bind(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("failed to serve the mock on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Serving the lemon.markets mock at {}", server.url());
+    server.join();
+}
@@ -0,0 +1,368 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Proxy, Url};
+
+use crate::client::{TradingClient, PAPER_ENDPOINT};
+use crate::data_client::{DataClient, DATA_ENDPOINT};
+use crate::error::Error;
+use crate::rate_limit::RateLimiter;
+use crate::request::bearer_auth;
+use crate::retry::RetryPolicy;
+use crate::risk::RiskGuard;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncReqwestTransport, AsyncTransport};
+use crate::transport::{ReqwestTransport, Transport};
+
+/// User agent sent when none is configured
+static DEFAULT_USER_AGENT: &str = concat!("septoria/", env!("CARGO_PKG_VERSION"));
+
+/// Builder for a [`TradingClient`] or a [`DataClient`].
+///
+/// Use it through [`TradingClientBuilder`] and [`DataClientBuilder`], or through
+/// [`TradingClient::builder`] and [`DataClient::builder`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder<C> {
+    /// Settings shared by every client
+    config: ClientConfig,
+    /// The client that will be built
+    client: PhantomData<fn() -> C>,
+}
+
+/// Builder for a [`TradingClient`]. Defaults to the paper trading endpoint.
+pub type TradingClientBuilder = ClientBuilder<TradingClient>;
+/// Builder for a [`DataClient`]. Defaults to the market data endpoint.
+pub type DataClientBuilder = ClientBuilder<DataClient>;
+
+/// Transport settings shared by the trading and data clients
+#[derive(Debug, Clone)]
+pub(crate) struct ClientConfig {
+    /// The API key
+    pub(crate) api_key: String,
+    /// The base url for the API
+    pub(crate) base_url: String,
+    /// Timeout for a whole request
+    pub(crate) timeout: Option<Duration>,
+    /// Timeout for establishing a connection
+    pub(crate) connect_timeout: Option<Duration>,
+    /// User agent sent with every request
+    pub(crate) user_agent: String,
+    /// Proxies all requests go through
+    pub(crate) proxies: Vec<Proxy>,
+    /// Whether proxies from the environment are ignored
+    pub(crate) no_proxy: bool,
+    /// Extra headers sent with every request
+    pub(crate) headers: Vec<(String, String)>,
+    /// Policy for retrying failed requests
+    pub(crate) retry: RetryPolicy,
+    /// Limiter every request waits for, if any
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    /// Checks orders are run through before they are sent, if any. Trading clients only
+    pub(crate) risk_guard: Option<RiskGuard>,
+}
+
+impl<C> ClientBuilder<C> {
+    /// Create a builder for the given API key and base url
+    fn with_endpoint(api_key: String, base_url: &str) -> Self {
+        Self {
+            config: ClientConfig {
+                api_key,
+                base_url: base_url.to_string(),
+                timeout: None,
+                connect_timeout: None,
+                user_agent: DEFAULT_USER_AGENT.to_string(),
+                proxies: vec![],
+                no_proxy: false,
+                headers: vec![],
+                retry: RetryPolicy::default(),
+                rate_limiter: None,
+                risk_guard: None,
+            },
+            client: PhantomData,
+        }
+    }
+
+    /// Set the base url, e.g. a local mock server
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.base_url = base_url.into();
+        self
+    }
+
+    /// Set a timeout for every request, from connecting until the body has been read
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Set a timeout for establishing a connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the user agent. Defaults to `septoria/<version>`
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.user_agent = user_agent.into();
+        self
+    }
+
+    /// Send all requests through the given proxy
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.config.proxies.push(proxy);
+        self
+    }
+
+    /// Ignore proxies configured through the environment
+    pub fn no_proxy(mut self) -> Self {
+        self.config.no_proxy = true;
+        self
+    }
+
+    /// Send an extra header with every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the policy for retrying failed requests. Defaults to [`RetryPolicy::default`]
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.config.retry = retry;
+        self
+    }
+
+    /// Make every request wait for the given rate limiter.
+    ///
+    /// Pass clones of the same limiter to all clients that share an API key.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.config.rate_limiter = Some(rate_limiter);
+        self
+    }
+}
+
+impl ClientBuilder<TradingClient> {
+    /// Create a builder for the paper trading endpoint
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_endpoint(api_key.into(), PAPER_ENDPOINT)
+    }
+
+    /// Check every order with `guard` before it is sent.
+    ///
+    /// Orders that fail the checks are rejected with
+    /// [`Error::RiskCheck`](crate::error::Error::RiskCheck) instead of being sent.
+    pub fn risk_guard(mut self, guard: RiskGuard) -> Self {
+        self.config.risk_guard = Some(guard);
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<TradingClient, Error> {
+        let transport = ReqwestTransport::new(self.config.build_blocking()?);
+        self.build_with_transport(transport)
+    }
+
+    /// Build the client on a custom transport.
+    ///
+    /// The timeouts, user agent, proxies and extra headers only configure the default
+    /// transport, so they are ignored here.
+    pub fn build_with_transport<T: Transport>(
+        self,
+        transport: T,
+    ) -> Result<TradingClient<T>, Error> {
+        bearer_auth(&self.config.api_key)?;
+        self.config.risk_guard_url()?;
+        Ok(TradingClient {
+            base_url: self.config.base_url()?,
+            transport,
+            retry: self.config.retry,
+            rate_limiter: self.config.rate_limiter,
+            risk_guard: self.config.risk_guard,
+            api_key: self.config.api_key,
+        })
+    }
+
+    /// Build the async client
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<crate::client::AsyncTradingClient, Error> {
+        let transport = AsyncReqwestTransport::new(self.config.build_async()?);
+        self.build_async_with_transport(transport)
+    }
+
+    /// Build the async client on a custom transport.
+    ///
+    /// The timeouts, user agent, proxies and extra headers only configure the default
+    /// transport, so they are ignored here.
+    #[cfg(feature = "async")]
+    pub fn build_async_with_transport<T: AsyncTransport>(
+        self,
+        transport: T,
+    ) -> Result<crate::client::AsyncTradingClient<T>, Error> {
+        bearer_auth(&self.config.api_key)?;
+        self.config.risk_guard_url()?;
+        Ok(crate::client::AsyncTradingClient {
+            base_url: self.config.base_url()?,
+            transport,
+            retry: self.config.retry,
+            rate_limiter: self.config.rate_limiter,
+            risk_guard: self.config.risk_guard,
+            api_key: self.config.api_key,
+        })
+    }
+}
+
+impl ClientBuilder<DataClient> {
+    /// Create a builder for the market data endpoint
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_endpoint(api_key.into(), DATA_ENDPOINT)
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<DataClient, Error> {
+        let transport = ReqwestTransport::new(self.config.build_blocking()?);
+        self.build_with_transport(transport)
+    }
+
+    /// Build the client on a custom transport.
+    ///
+    /// The timeouts, user agent, proxies and extra headers only configure the default
+    /// transport, so they are ignored here.
+    pub fn build_with_transport<T: Transport>(self, transport: T) -> Result<DataClient<T>, Error> {
+        bearer_auth(&self.config.api_key)?;
+        Ok(DataClient {
+            base_url: self.config.base_url()?,
+            transport,
+            retry: self.config.retry,
+            rate_limiter: self.config.rate_limiter,
+            api_key: self.config.api_key,
+        })
+    }
+
+    /// Build the async client
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<crate::data_client::AsyncDataClient, Error> {
+        let transport = AsyncReqwestTransport::new(self.config.build_async()?);
+        self.build_async_with_transport(transport)
+    }
+
+    /// Build the async client on a custom transport.
+    ///
+    /// The timeouts, user agent, proxies and extra headers only configure the default
+    /// transport, so they are ignored here.
+    #[cfg(feature = "async")]
+    pub fn build_async_with_transport<T: AsyncTransport>(
+        self,
+        transport: T,
+    ) -> Result<crate::data_client::AsyncDataClient<T>, Error> {
+        bearer_auth(&self.config.api_key)?;
+        Ok(crate::data_client::AsyncDataClient {
+            base_url: self.config.base_url()?,
+            transport,
+            retry: self.config.retry,
+            rate_limiter: self.config.rate_limiter,
+            api_key: self.config.api_key,
+        })
+    }
+}
+
+impl ClientConfig {
+    /// Parse the configured base url
+    pub(crate) fn base_url(&self) -> Result<Url, Error> {
+        Url::parse(&self.base_url)
+            .map_err(|e| Error::Config(format!("invalid base url {:?}: {}", self.base_url, e)))
+    }
+
+    /// Check that the market data url of the risk guard is valid, if there is a guard
+    fn risk_guard_url(&self) -> Result<(), Error> {
+        match &self.risk_guard {
+            Some(guard) => guard.parsed_data_url().map(drop),
+            None => Ok(()),
+        }
+    }
+
+    /// The extra headers sent with every request
+    pub(crate) fn headers(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::Config(format!("invalid header name {:?}", name)))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|_| Error::Config(format!("invalid value for header {:?}", name)))?;
+            headers.insert(header_name, header_value);
+        }
+        Ok(headers)
+    }
+
+    /// Build a blocking reqwest client with these settings
+    pub(crate) fn build_blocking(&self) -> Result<reqwest::blocking::Client, Error> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .default_headers(self.headers()?)
+            .user_agent(self.user_agent.as_str());
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Build an async reqwest client with these settings
+    #[cfg(feature = "async")]
+    pub(crate) fn build_async(&self) -> Result<reqwest::Client, Error> {
+        let mut builder = reqwest::Client::builder()
+            .default_headers(self.headers()?)
+            .user_agent(self.user_agent.as_str());
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::client::TradingClient;
+    use crate::data_client::DataClient;
+    use crate::error::Error;
+
+    #[test]
+    fn test_builder_honors_base_url() {
+        let client = TradingClient::builder("key")
+            .base_url("http://localhost:8080/v1")
+            .timeout(Duration::from_secs(5))
+            .header("X-Strategy", "momentum")
+            .build()
+            .unwrap();
+        assert_eq!(client.base_url.as_str(), "http://localhost:8080/v1");
+
+        let client = TradingClient::live_client("key".to_string());
+        assert_eq!(client.base_url.as_str(), "https://trading.lemon.markets/v1");
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let client = DataClient::builder("key").base_url("not a url").build();
+        assert!(matches!(client, Err(Error::Config(_))));
+        let client = DataClient::builder("key\n").build();
+        assert!(matches!(client, Err(Error::Config(_))));
+        let client = DataClient::builder("key").header("bad header", "x").build();
+        assert!(matches!(client, Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,256 @@
+//! Trading hours of a venue
+//!
+//! A [`TradingCalendar`] turns the `opening_days` and `opening_hours` of a
+//! [`VenueData`] into sessions in UTC, so it can answer whether the venue is open without
+//! calling the API again. Opening hours are interpreted in the timezone of the venue, so the
+//! sessions move with daylight saving time.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), septoria::error::Error> {
+//! use septoria::calendar::TradingCalendar;
+//! use septoria::data_client::DataClient;
+//!
+//! let client = DataClient::new("<API key>".to_string());
+//! let venues = client.get_venues()?.results.unwrap_or_default();
+//! let calendar = TradingCalendar::from_venue(&venues[0])?;
+//! if !calendar.is_open() {
+//!     println!("{} opens at {:?}", calendar.mic(), calendar.next_open());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::prelude::*;
+use chrono::Duration;
+use chrono_tz::Tz;
+
+use crate::api::market_data::venues::VenueData;
+use crate::error::Error;
+use crate::identifiers::Mic;
+
+/// A single trading session of a venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// The day of the session, in the timezone of the venue
+    pub date: NaiveDate,
+    /// When the venue opens
+    pub open: DateTime<Utc>,
+    /// When the venue closes
+    pub close: DateTime<Utc>,
+}
+
+impl Session {
+    /// Whether the venue is open at `time` during this session
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.open <= time && time < self.close
+    }
+}
+
+/// The upcoming trading sessions of a venue
+///
+/// Only the opening days the API returned are known, so the venue counts as closed after the
+/// last of them. Build a new calendar from fresh venue data to look further ahead.
+#[derive(Debug, Clone)]
+pub struct TradingCalendar {
+    /// MIC of the venue
+    mic: Mic,
+    /// Timezone of the venue
+    tz: Tz,
+    /// The sessions, ordered by time
+    sessions: Vec<Session>,
+}
+
+impl TradingCalendar {
+    /// Build the calendar of a venue from its opening days and hours
+    pub fn from_venue(venue: &VenueData) -> Result<Self, Error> {
+        let hours = &venue.opening_hours;
+        let tz = hours.tz()?;
+        let start = hours.open_time()?;
+        let end = hours.close_time()?;
+
+        let mut days = venue.opening_days.clone();
+        days.sort_unstable();
+        days.dedup();
+        let mut sessions = vec![];
+        for date in days {
+            // Sessions that end at or before their start run past midnight
+            let close_date = match end > start {
+                true => date,
+                false => date + Duration::days(1),
+            };
+            let (Some(open), Some(close)) = (
+                local_to_utc(tz, date.and_time(start)),
+                local_to_utc(tz, close_date.and_time(end)),
+            ) else {
+                continue;
+            };
+            sessions.push(Session { date, open, close });
+        }
+        Ok(Self {
+            mic: venue.mic.clone(),
+            tz,
+            sessions,
+        })
+    }
+
+    /// MIC of the venue
+    pub fn mic(&self) -> &Mic {
+        &self.mic
+    }
+
+    /// Timezone the opening hours of the venue are in
+    pub fn timezone(&self) -> Tz {
+        self.tz
+    }
+
+    /// The known sessions, ordered by time
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// The session the venue is in at `time`, if it is open
+    pub fn session_at(&self, time: DateTime<Utc>) -> Option<&Session> {
+        self.sessions.iter().find(|session| session.contains(time))
+    }
+
+    /// Whether the venue is open at `time`
+    pub fn is_open_at(&self, time: DateTime<Utc>) -> bool {
+        self.session_at(time).is_some()
+    }
+
+    /// Whether the venue is open right now
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Utc::now())
+    }
+
+    /// When the venue opens next after `time`. `None` if no known session opens later
+    pub fn next_open_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.sessions
+            .iter()
+            .map(|session| session.open)
+            .find(|open| *open > time)
+    }
+
+    /// When the venue opens next. `None` if no known session opens later
+    pub fn next_open(&self) -> Option<DateTime<Utc>> {
+        self.next_open_after(Utc::now())
+    }
+
+    /// When the venue closes next after `time`: the end of the current session if it is
+    /// open, and the end of the next session otherwise
+    pub fn next_close_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.sessions
+            .iter()
+            .map(|session| session.close)
+            .find(|close| *close > time)
+    }
+
+    /// When the venue closes next
+    pub fn next_close(&self) -> Option<DateTime<Utc>> {
+        self.next_close_after(Utc::now())
+    }
+
+    /// The number of trading days from `from` to `to`, both included, as dates in the
+    /// timezone of the venue
+    pub fn trading_days_between(&self, from: NaiveDate, to: NaiveDate) -> usize {
+        self.sessions
+            .iter()
+            .filter(|session| from <= session.date && session.date <= to)
+            .count()
+    }
+}
+
+/// Convert a local time of the venue to UTC.
+///
+/// Times that occur twice when the clocks go back resolve to the first occurrence. Times that
+/// are skipped when the clocks go forward are moved forward by the length of the gap.
+fn local_to_utc(tz: Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    (0..=2)
+        .filter_map(|hours| {
+            tz.from_local_datetime(&(local + Duration::hours(hours)))
+                .earliest()
+        })
+        .next()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::*;
+
+    use super::TradingCalendar;
+    use crate::api::market_data::venues::{OpeningHours, VenueData};
+
+    /// A venue open from `start` to `end` in Berlin on the given days of March 2022
+    fn venue(start: &str, end: &str, days: &[u32]) -> VenueData {
+        VenueData {
+            name: "Lemon Markets Best Price".to_string(),
+            title: "LMBPX".to_string(),
+            mic: "LMBPX".parse().unwrap(),
+            is_open: false,
+            opening_hours: OpeningHours {
+                start: start.to_string(),
+                end: end.to_string(),
+                timezone: "Europe/Berlin".to_string(),
+            },
+            opening_days: days
+                .iter()
+                .map(|day| NaiveDate::from_ymd_opt(2022, 3, *day).unwrap())
+                .collect(),
+        }
+    }
+
+    /// A time on the given day of March 2022, in UTC
+    fn utc(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 3, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_sessions_follow_daylight_saving_time() {
+        // Berlin switches from CET (UTC+1) to CEST (UTC+2) on Sunday, March 27 2022
+        let calendar = TradingCalendar::from_venue(&venue("08:00", "22:00", &[25, 28])).unwrap();
+        let sessions = calendar.sessions();
+        assert_eq!(
+            (sessions[0].open, sessions[0].close),
+            (utc(25, 7, 0), utc(25, 21, 0))
+        );
+        assert_eq!(
+            (sessions[1].open, sessions[1].close),
+            (utc(28, 6, 0), utc(28, 20, 0))
+        );
+
+        assert!(calendar.is_open_at(utc(25, 7, 0)));
+        assert!(!calendar.is_open_at(utc(25, 21, 0)));
+        assert!(!calendar.is_open_at(utc(28, 5, 59)));
+        assert!(calendar.is_open_at(utc(28, 6, 0)));
+    }
+
+    #[test]
+    fn test_next_open_and_close() {
+        let calendar = TradingCalendar::from_venue(&venue("07:30", "23:00", &[4, 7])).unwrap();
+        // Friday during the session
+        assert_eq!(
+            calendar.next_close_after(utc(4, 12, 0)),
+            Some(utc(4, 22, 0))
+        );
+        assert_eq!(calendar.next_open_after(utc(4, 12, 0)), Some(utc(7, 6, 30)));
+        // Saturday, closed for the weekend
+        assert_eq!(calendar.next_open_after(utc(5, 12, 0)), Some(utc(7, 6, 30)));
+        assert_eq!(
+            calendar.next_close_after(utc(5, 12, 0)),
+            Some(utc(7, 22, 0))
+        );
+        // After the last known session
+        assert_eq!(calendar.next_open_after(utc(8, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_trading_days_between() {
+        let calendar =
+            TradingCalendar::from_venue(&venue("08:00", "22:00", &[4, 7, 8, 9, 10, 11])).unwrap();
+        let day = |day| NaiveDate::from_ymd_opt(2022, 3, day).unwrap();
+        assert_eq!(calendar.trading_days_between(day(4), day(11)), 6);
+        assert_eq!(calendar.trading_days_between(day(5), day(8)), 2);
+        assert_eq!(calendar.trading_days_between(day(12), day(20)), 0);
+    }
+}
@@ -0,0 +1,372 @@
+//! Record and replay HTTP interactions
+//!
+//! A [`RecordingTransport`] sends requests through another transport and captures every
+//! request/response pair into a [`Cassette`], a JSON fixture file. A [`ReplayTransport`] serves
+//! the pairs of a cassette back without network access, which pins the exact payloads the
+//! response types have to deserialize.
+//!
+//! ```no_run
+//! use septoria::cassette::{RecordingTransport, ReplayTransport};
+//! use septoria::client::TradingClient;
+//! use septoria::transport::ReqwestTransport;
+//!
+//! # fn main() -> Result<(), septoria::error::Error> {
+//! // Record once against the real API
+//! let recorder = RecordingTransport::new("cassettes/account.json", ReqwestTransport::default());
+//! let client = TradingClient::builder("<API key>").build_with_transport(recorder)?;
+//! client.get_account_information()?;
+//! drop(client);
+//!
+//! // Replay offline
+//! let replay = ReplayTransport::load("cassettes/account.json")?;
+//! let client = TradingClient::builder("any key").build_with_transport(replay)?;
+//! client.get_account_information()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+#[cfg(feature = "async")]
+use crate::transport::AsyncTransport;
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+
+/// What the `Authorization` header is recorded as
+static REDACTED: &str = "Bearer [REDACTED]";
+
+/// Recorded request/response pairs, as stored in a fixture file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    /// The interactions, in the order they were recorded
+    pub interactions: Vec<Interaction>,
+}
+
+/// A single recorded request and the response to it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    /// The request that was sent
+    pub request: RecordedRequest,
+    /// The response that was received
+    pub response: RecordedResponse,
+}
+
+/// A recorded request, with the bearer token redacted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedRequest {
+    /// HTTP method, e.g. `GET`
+    pub method: String,
+    /// Full url, including the query
+    pub url: String,
+    /// Headers of the request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Json body, if the request has one
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// A recorded response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Headers of the response
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The body, as json if it is valid json, and as a string otherwise
+    pub body: Value,
+}
+
+impl Cassette {
+    /// Read a cassette from a fixture file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&file)?)
+    }
+
+    /// Write the cassette to a fixture file, creating missing directories
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl RecordedRequest {
+    /// Record a request, with the bearer token redacted
+    fn record(request: &HttpRequest) -> Self {
+        let mut headers = record_headers(&request.headers);
+        if headers.contains_key(AUTHORIZATION.as_str()) {
+            headers.insert(AUTHORIZATION.to_string(), REDACTED.to_string());
+        }
+        Self {
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            headers,
+            body: request.body.as_deref().map(record_body),
+        }
+    }
+
+    /// Whether this recording is of the given request.
+    ///
+    /// The host is ignored, so a cassette recorded against the API can be replayed with any
+    /// base url. Json bodies are compared by value.
+    fn matches(&self, request: &HttpRequest) -> bool {
+        let Ok(url) = Url::parse(&self.url) else {
+            return false;
+        };
+        self.method == request.method.as_str()
+            && url.path() == request.url.path()
+            && url.query() == request.url.query()
+            && self.body == request.body.as_deref().map(record_body)
+    }
+}
+
+impl RecordedResponse {
+    /// Record a response
+    fn record(response: &HttpResponse) -> Self {
+        Self {
+            status: response.status.as_u16(),
+            headers: record_headers(&response.headers),
+            body: record_body(&String::from_utf8_lossy(&response.body)),
+        }
+    }
+
+    /// Turn the recording back into a response
+    fn replay(&self) -> Result<HttpResponse, Error> {
+        let status = StatusCode::from_u16(self.status)
+            .map_err(|_| Error::Str(format!("invalid recorded status {}", self.status)))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        let body = match &self.body {
+            Value::String(text) => text.clone().into_bytes(),
+            json => serde_json::to_vec(json)?,
+        };
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Headers with a text value, by name
+fn record_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// A body as json if it is valid json, and as a string otherwise
+fn record_body(body: &str) -> Value {
+    serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()))
+}
+
+/// Sends requests through another transport, and records them into a cassette
+///
+/// The cassette is written when the transport is dropped, or with
+/// [`RecordingTransport::save`]. The `Authorization` header is redacted, so the cassette can
+/// be committed.
+pub struct RecordingTransport<T> {
+    /// Where the cassette is written to
+    path: PathBuf,
+    /// The transport that actually sends the requests
+    inner: T,
+    /// The interactions recorded so far
+    cassette: Mutex<Cassette>,
+}
+
+impl<T> RecordingTransport<T> {
+    /// Record the requests sent through `inner` into the cassette at `path`
+    pub fn new(path: impl Into<PathBuf>, inner: T) -> Self {
+        Self {
+            path: path.into(),
+            inner,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Write the interactions recorded so far to the cassette file
+    pub fn save(&self) -> Result<(), Error> {
+        self.lock().save(&self.path)
+    }
+
+    /// Record the outcome of a request. Failed requests have no response to record.
+    fn record(&self, request: &HttpRequest, result: &Result<HttpResponse, Error>) {
+        if let Ok(response) = result {
+            self.lock().interactions.push(Interaction {
+                request: RecordedRequest::record(request),
+                response: RecordedResponse::record(response),
+            });
+        }
+    }
+
+    /// Lock the cassette, even if a request panicked while holding the lock
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cassette> {
+        self.cassette
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T> Drop for RecordingTransport<T> {
+    fn drop(&mut self) {
+        // Call `save` to handle the error
+        let _ = self.save();
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let result = self.inner.send(request.clone());
+        self.record(&request, &result);
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: AsyncTransport + Sync> AsyncTransport for RecordingTransport<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let result = self.inner.send(request.clone()).await;
+        self.record(&request, &result);
+        result
+    }
+}
+
+/// Serves the responses of a cassette, without network access
+///
+/// Every recorded interaction is served once, to the first request with the same method,
+/// path, query and body. Requests without a matching interaction fail with [`Error::Str`].
+#[derive(Debug)]
+pub struct ReplayTransport {
+    /// The interactions that have not been served yet
+    remaining: Mutex<Vec<Interaction>>,
+}
+
+impl ReplayTransport {
+    /// Replay the cassette at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    /// Replay the given cassette
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            remaining: Mutex::new(cassette.interactions),
+        }
+    }
+
+    /// The number of recorded interactions that have not been served yet
+    pub fn remaining(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Serve the first matching interaction
+    fn replay(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let mut remaining = self.lock();
+        let index = remaining
+            .iter()
+            .position(|interaction| interaction.request.matches(request))
+            .ok_or_else(|| {
+                Error::Str(format!(
+                    "no recorded interaction for {} {}",
+                    request.method, request.url
+                ))
+            })?;
+        remaining.remove(index).response.replay()
+    }
+
+    /// Lock the interactions, even if a request panicked while holding the lock
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Interaction>> {
+        self.remaining
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        self.replay(&request)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransport for ReplayTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        self.replay(&request)
+    }
+}
+
+/// Path of a cassette in the crate's `tests/cassettes` directory
+#[cfg(test)]
+pub(crate) fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::AUTHORIZATION;
+
+    use super::{Cassette, RecordingTransport, ReplayTransport};
+    use crate::client::TradingClient;
+    use crate::mock::MockServer;
+    use crate::transport::ReqwestTransport;
+
+    #[test]
+    fn test_record_then_replay() {
+        let server = MockServer::start().unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "septoria-cassette-{}-{}.json",
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+
+        let recorder = RecordingTransport::new(&path, ReqwestTransport::default());
+        let client = TradingClient::builder("secret key")
+            .base_url(server.url())
+            .build_with_transport(recorder)
+            .unwrap();
+        let recorded = client.get_account_information().unwrap();
+        drop(client);
+        drop(server);
+
+        let cassette = Cassette::load(&path).unwrap();
+        let request = &cassette.interactions[0].request;
+        assert_eq!(request.headers[AUTHORIZATION.as_str()], "Bearer [REDACTED]");
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("secret key"));
+
+        let replay = ReplayTransport::new(cassette);
+        let client = TradingClient::builder("other key")
+            .base_url("https://example.com/v1")
+            .build_with_transport(replay)
+            .unwrap();
+        let replayed = client.get_account_information().unwrap();
+        assert_eq!(replayed.results.account_id, recorded.results.account_id);
+        assert_eq!(client.transport.remaining(), 0);
+        assert!(client.get_account_information().is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
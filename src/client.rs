@@ -1,75 +1,56 @@
-use crate::api::Requests;
+use crate::api::PaginationResponse;
+use crate::builder::TradingClientBuilder;
 use crate::error::Error;
-use crate::util::build_reqwest_client;
+use crate::pagination::Paginator;
+use crate::rate_limit::RateLimiter;
+use crate::request::Request;
+use crate::retry::RetryPolicy;
+use crate::risk::RiskGuard;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncReqwestTransport, AsyncTransport, BodyStream};
+use crate::transport::{BodyReader, HttpResponse, ReqwestTransport, Transport};
 use reqwest::Url;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use std::fmt::Debug;
 
 /// Paper endpoint url
-static PAPER_ENDPOINT: &str = "https://paper-trading.lemon.markets/v1";
+pub(crate) static PAPER_ENDPOINT: &str = "https://paper-trading.lemon.markets/v1";
 /// Money endpoint url
-static MONEY_ENDPOINT: &str = "https://trading.lemon.markets/v1";
+pub(crate) static MONEY_ENDPOINT: &str = "https://trading.lemon.markets/v1";
 
 #[derive(Debug)]
 /// The client for the Lemon API.
-pub struct TradingClient {
+pub struct TradingClient<T = ReqwestTransport> {
     /// The API key.
     pub api_key: String,
     /// The base url for the API
     pub base_url: Url,
-    /// Internal client used for all requests.
-    pub(crate) client: reqwest::blocking::Client,
+    /// Transport all requests are sent through
+    pub(crate) transport: T,
+    /// Policy for retrying failed requests
+    pub(crate) retry: RetryPolicy,
+    /// Limiter every request waits for, if any
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    /// Checks every order is run through before it is sent, if any
+    pub(crate) risk_guard: Option<RiskGuard>,
 }
 
-/// API methods for the Client
-impl Requests for TradingClient {
-    /// Generic get request
-    fn get<T: DeserializeOwned + Debug>(&self, path: &str) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let r = self.client.get(&url).send()?;
-        let json = self.response_handler(r)?;
-        Ok(json)
-    }
-    /// Generic get request with query parameters
-    fn get_with_query<T: DeserializeOwned + Debug, Q: IntoIterator + Serialize>(
-        &self,
-        path: &str,
-        query: Q,
-    ) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let r = self.client.get(&url).query(&query).send()?;
-        let json = self.response_handler(r)?;
-        Ok(json)
-    }
-    /// Generic post request
-    fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: B) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let body_string = serde_json::to_string(&body)?;
-        let r = self.client.post(&url).body(body_string).send()?;
-        let json = self.response_handler(r)?;
-        Ok(json)
-    }
-    /// Generic delete request
-    fn delete<T: DeserializeOwned>(&self, path: &str, path_param: &str) -> Result<T, Error> {
-        let url = format!("{}/{}/{}", self.base_url, path, path_param);
-        let r = self.client.delete(&url).send()?;
-        // .json::<T>()?;
-        let json = self.response_handler::<T>(r)?;
-        Ok(json)
+impl TradingClient {
+    /// Create a builder to configure the endpoint and transport settings of the client
+    pub fn builder(api_key: impl Into<String>) -> TradingClientBuilder {
+        TradingClientBuilder::new(api_key)
     }
-}
 
-impl TradingClient {
-    /// Create a new TradingClient
-    pub fn new(api_key: String, _endpoint: &str) -> Self {
-        let base_url = Url::parse(PAPER_ENDPOINT).unwrap();
-        let client = build_reqwest_client(&api_key);
-        Self {
-            api_key,
-            base_url,
-            client,
-        }
+    /// Create a new TradingClient for the given endpoint
+    ///
+    /// # Panics
+    ///
+    /// Panics if the API key or the endpoint is invalid. Use [`TradingClient::builder`] to
+    /// handle these errors instead.
+    pub fn new(api_key: String, endpoint: &str) -> Self {
+        TradingClient::builder(api_key)
+            .base_url(endpoint)
+            .build()
+            .expect("invalid API key or endpoint")
     }
 
     /// Create a new client for paper trading with the given API key.
@@ -82,3 +63,165 @@ impl TradingClient {
         TradingClient::new(api_key, MONEY_ENDPOINT)
     }
 }
+
+impl<Tr: Transport> TradingClient<Tr> {
+    /// Iterate over the items of `first_page` and of all following pages.
+    /// Further pages are fetched as the iterator reaches them.
+    pub fn paginate<T: DeserializeOwned>(
+        &self,
+        first_page: PaginationResponse<T>,
+    ) -> Paginator<'_, T> {
+        Paginator::new(first_page, move |next| {
+            self.send(Request::follow(next, &self.base_url)?)
+        })
+    }
+
+    /// Send a request through the shared request core
+    pub(crate) fn send<T: DeserializeOwned>(&self, request: Request<T>) -> Result<T, Error> {
+        self.send_to(&self.base_url, request)
+    }
+
+    /// Send a request to another API with the same key and transport, e.g. the market data API
+    pub(crate) fn send_to<T: DeserializeOwned>(
+        &self,
+        base_url: &Url,
+        request: Request<T>,
+    ) -> Result<T, Error> {
+        request.send_blocking(
+            &self.transport,
+            base_url,
+            &self.api_key,
+            &self.retry,
+            self.rate_limiter.as_ref(),
+        )
+    }
+
+    /// Send a request and return the response with its body unread, e.g. to download a file
+    pub(crate) fn send_streaming<T: DeserializeOwned>(
+        &self,
+        request: Request<T>,
+    ) -> Result<HttpResponse<BodyReader>, Error> {
+        request.send_blocking_streaming(
+            &self.transport,
+            &self.base_url,
+            &self.api_key,
+            &self.retry,
+            self.rate_limiter.as_ref(),
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+#[derive(Debug)]
+/// The async client for the Lemon API.
+///
+/// Offers the same endpoints as [`TradingClient`], as `async fn`s.
+pub struct AsyncTradingClient<T = AsyncReqwestTransport> {
+    /// The API key.
+    pub api_key: String,
+    /// The base url for the API
+    pub base_url: Url,
+    /// Transport all requests are sent through
+    pub(crate) transport: T,
+    /// Policy for retrying failed requests
+    pub(crate) retry: RetryPolicy,
+    /// Limiter every request waits for, if any
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    /// Checks every order is run through before it is sent, if any
+    pub(crate) risk_guard: Option<RiskGuard>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncTradingClient {
+    /// Create a builder to configure the endpoint and transport settings of the client
+    pub fn builder(api_key: impl Into<String>) -> TradingClientBuilder {
+        TradingClientBuilder::new(api_key)
+    }
+
+    /// Create a new AsyncTradingClient for the given endpoint
+    ///
+    /// # Panics
+    ///
+    /// Panics if the API key or the endpoint is invalid. Use [`AsyncTradingClient::builder`]
+    /// to handle these errors instead.
+    pub fn new(api_key: String, endpoint: &str) -> Self {
+        AsyncTradingClient::builder(api_key)
+            .base_url(endpoint)
+            .build_async()
+            .expect("invalid API key or endpoint")
+    }
+
+    /// Create a new async client for paper trading with the given API key.
+    pub fn paper_client(api_key: &str) -> Self {
+        AsyncTradingClient::new(api_key.to_string(), PAPER_ENDPOINT)
+    }
+
+    /// Create a new async client for live trading with the given API key.
+    pub fn live_client(api_key: String) -> Self {
+        AsyncTradingClient::new(api_key, MONEY_ENDPOINT)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncTradingClient<Tr> {
+    /// Stream the items of `first_page` and of all following pages.
+    /// Further pages are fetched as the stream reaches them.
+    pub fn paginate<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        first_page: PaginationResponse<T>,
+    ) -> impl futures_util::Stream<Item = Result<T, Error>> + 'a {
+        crate::pagination::stream(first_page, move |next| async move {
+            self.send(Request::follow(&next, &self.base_url)?).await
+        })
+    }
+
+    /// Collect the items of `first_page` and of following pages, up to `max_pages` pages
+    pub async fn collect_all<T: DeserializeOwned>(
+        &self,
+        first_page: PaginationResponse<T>,
+        max_pages: usize,
+    ) -> Result<Vec<T>, Error> {
+        crate::pagination::collect_all(first_page, max_pages, |next| async move {
+            self.send(Request::follow(&next, &self.base_url)?).await
+        })
+        .await
+    }
+
+    /// Send a request through the shared request core
+    pub(crate) async fn send<T: DeserializeOwned>(&self, request: Request<T>) -> Result<T, Error> {
+        self.send_to(&self.base_url, request).await
+    }
+
+    /// Send a request to another API with the same key and transport, e.g. the market data API
+    pub(crate) async fn send_to<T: DeserializeOwned>(
+        &self,
+        base_url: &Url,
+        request: Request<T>,
+    ) -> Result<T, Error> {
+        request
+            .send_async(
+                &self.transport,
+                base_url,
+                &self.api_key,
+                &self.retry,
+                self.rate_limiter.as_ref(),
+            )
+            .await
+    }
+
+    /// Send a request and return the response with its body unread, e.g. to download a file
+    pub(crate) async fn send_streaming<T: DeserializeOwned>(
+        &self,
+        request: Request<T>,
+    ) -> Result<HttpResponse<BodyStream>, Error> {
+        request
+            .send_async_streaming(
+                &self.transport,
+                &self.base_url,
+                &self.api_key,
+                &self.retry,
+                self.rate_limiter.as_ref(),
+            )
+            .await
+    }
+}
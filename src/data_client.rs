@@ -1,70 +1,150 @@
-use crate::api::Requests;
+use crate::api::PaginationResponse;
+use crate::builder::DataClientBuilder;
 use crate::error::Error;
+use crate::pagination::Paginator;
+use crate::rate_limit::RateLimiter;
+use crate::request::Request;
+use crate::retry::RetryPolicy;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncReqwestTransport, AsyncTransport};
+use crate::transport::{ReqwestTransport, Transport};
 use reqwest::Url;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
-use std::fmt::Debug;
 
-use crate::util::build_reqwest_client;
-
-static DATA_ENDPOINT: &str = "https://data.lemon.markets/v1/";
+/// Market data endpoint url
+pub(crate) static DATA_ENDPOINT: &str = "https://data.lemon.markets/v1/";
 
 #[derive(Debug)]
 /// The data client for the Lemon API.
-pub struct DataClient {
+pub struct DataClient<T = ReqwestTransport> {
     /// The API key.
     pub api_key: String,
     /// The base url for the API
     pub base_url: Url,
-    /// Internal client used for all requests.
-    pub(crate) client: reqwest::blocking::Client,
+    /// Transport all requests are sent through
+    pub(crate) transport: T,
+    /// Policy for retrying failed requests
+    pub(crate) retry: RetryPolicy,
+    /// Limiter every request waits for, if any
+    pub(crate) rate_limiter: Option<RateLimiter>,
 }
 
 impl DataClient {
+    /// Create a builder to configure the endpoint and transport settings of the client
+    pub fn builder(api_key: impl Into<String>) -> DataClientBuilder {
+        DataClientBuilder::new(api_key)
+    }
+
     /// Create a new data client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the API key is invalid. Use [`DataClient::builder`] to handle this error
+    /// instead.
     pub fn new(api_key: String) -> Self {
-        let client = build_reqwest_client(&api_key);
-        Self {
-            api_key,
-            base_url: Url::parse(DATA_ENDPOINT).unwrap(),
-            client,
-        }
+        DataClient::builder(api_key)
+            .build()
+            .expect("invalid API key")
     }
 }
 
-impl Requests for DataClient {
-    /// Generic get request
-    fn get<T: DeserializeOwned + Debug>(&self, path: &str) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let r = self.client.get(&url).send()?;
-        let json = self.response_handler(r)?;
-        Ok(json)
-    }
-    /// Generic get request with query parameters
-    fn get_with_query<T: DeserializeOwned + Debug, Q: IntoIterator + Serialize>(
+impl<Tr: Transport> DataClient<Tr> {
+    /// Iterate over the items of `first_page` and of all following pages.
+    /// Further pages are fetched as the iterator reaches them.
+    pub fn paginate<T: DeserializeOwned>(
         &self,
-        path: &str,
-        query: Q,
-    ) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let r = self.client.get(&url).query(&query).send()?;
-        let json = self.response_handler(r)?;
-        dbg!(&json);
-        Ok(json)
+        first_page: PaginationResponse<T>,
+    ) -> Paginator<'_, T> {
+        Paginator::new(first_page, move |next| {
+            self.send(Request::follow(next, &self.base_url)?)
+        })
     }
-    /// Generic post request
-    fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: B) -> Result<T, Error> {
-        let url = format!("{}/{}", self.base_url, path);
-        let body_string = serde_json::to_string(&body)?;
-        let r = self.client.post(&url).body(body_string).send()?;
-        let json = self.response_handler(r)?;
-        Ok(json)
+
+    /// Send a request through the shared request core
+    pub(crate) fn send<T: DeserializeOwned>(&self, request: Request<T>) -> Result<T, Error> {
+        request.send_blocking(
+            &self.transport,
+            &self.base_url,
+            &self.api_key,
+            &self.retry,
+            self.rate_limiter.as_ref(),
+        )
     }
-    /// Generic delete request
-    fn delete<T: DeserializeOwned>(&self, path: &str, path_param: &str) -> Result<T, Error> {
-        let url = format!("{}/{}/{}", self.base_url, path, path_param);
-        let r = self.client.delete(&url).send()?;
-        let json = self.response_handler::<T>(r)?;
-        Ok(json)
+}
+
+#[cfg(feature = "async")]
+#[derive(Debug)]
+/// The async data client for the Lemon API.
+///
+/// Offers the same endpoints as [`DataClient`], as `async fn`s.
+pub struct AsyncDataClient<T = AsyncReqwestTransport> {
+    /// The API key.
+    pub api_key: String,
+    /// The base url for the API
+    pub base_url: Url,
+    /// Transport all requests are sent through
+    pub(crate) transport: T,
+    /// Policy for retrying failed requests
+    pub(crate) retry: RetryPolicy,
+    /// Limiter every request waits for, if any
+    pub(crate) rate_limiter: Option<RateLimiter>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncDataClient {
+    /// Create a builder to configure the endpoint and transport settings of the client
+    pub fn builder(api_key: impl Into<String>) -> DataClientBuilder {
+        DataClientBuilder::new(api_key)
+    }
+
+    /// Create a new async data client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the API key is invalid. Use [`AsyncDataClient::builder`] to handle this
+    /// error instead.
+    pub fn new(api_key: String) -> Self {
+        AsyncDataClient::builder(api_key)
+            .build_async()
+            .expect("invalid API key")
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Tr: AsyncTransport> AsyncDataClient<Tr> {
+    /// Stream the items of `first_page` and of all following pages.
+    /// Further pages are fetched as the stream reaches them.
+    pub fn paginate<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        first_page: PaginationResponse<T>,
+    ) -> impl futures_util::Stream<Item = Result<T, Error>> + 'a {
+        crate::pagination::stream(first_page, move |next| async move {
+            self.send(Request::follow(&next, &self.base_url)?).await
+        })
+    }
+
+    /// Collect the items of `first_page` and of following pages, up to `max_pages` pages
+    pub async fn collect_all<T: DeserializeOwned>(
+        &self,
+        first_page: PaginationResponse<T>,
+        max_pages: usize,
+    ) -> Result<Vec<T>, Error> {
+        crate::pagination::collect_all(first_page, max_pages, |next| async move {
+            self.send(Request::follow(&next, &self.base_url)?).await
+        })
+        .await
+    }
+
+    /// Send a request through the shared request core
+    pub(crate) async fn send<T: DeserializeOwned>(&self, request: Request<T>) -> Result<T, Error> {
+        request
+            .send_async(
+                &self.transport,
+                &self.base_url,
+                &self.api_key,
+                &self.retry,
+                self.rate_limiter.as_ref(),
+            )
+            .await
     }
 }
@@ -3,7 +3,7 @@ use chrono::prelude::*;
 /// In general, an error will be returned in the following format.
 /// Lemons docks: [Error Handling](https://docs.lemon.markets/error-handling)
 use reqwest::{Error as ReqwestError, StatusCode};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Error as JsonError;
 use thiserror::Error;
 
@@ -22,17 +22,168 @@ pub enum Error {
     #[error("Encountered an Json related error")]
     Json(#[from] JsonError),
 
-    /// Error type for StatusCode errors
+    /// Error type for IO errors, e.g. while reading or writing a file
+    #[error("Encountered an IO related error")]
+    Io(#[from] std::io::Error),
+
+    /// Error type for StatusCode errors, with the raw response body.
+    /// Returned when an error response is not a lemon.markets error, e.g. a proxy error page.
     #[error("HTTP Error {0}")]
-    Http(StatusCode),
+    Http(StatusCode, String),
+
+    /// Error type for errors returned by the Lemon API
+    #[error("API Error {status}: {code}: {message}")]
+    Api {
+        /// HTTP status of the response
+        status: StatusCode,
+        /// Lemon API error code
+        code: ErrorCode,
+        /// Error message
+        message: String,
+        /// The time that the error occurred
+        time: DateTime<Utc>,
+        /// API mode.
+        mode: Mode,
+    },
+
+    /// Error type for an order that was rejected locally, before it was sent
+    #[error("Invalid order: {0}")]
+    InvalidOrder(#[from] crate::api::orders::InvalidOrder),
+
+    /// Error type for an order that failed the checks of a
+    /// [`RiskGuard`](crate::risk::RiskGuard), before it was sent
+    #[error("Order failed the risk checks: {0}")]
+    RiskCheck(crate::risk::RiskViolations),
+
+    /// Error type for an order that did not finish in time
+    #[error("Order {order_id} is still {status} after the timeout")]
+    OrderTimeout {
+        /// Id of the order
+        order_id: String,
+        /// The last status of the order
+        status: crate::api::orders::OrderStatus,
+    },
+
+    /// Error type for an order that moved to a status it cannot reach from its last one
+    #[error("Order {order_id} went from {from} to {to}, which is not a legal transition")]
+    InvalidOrderTransition {
+        /// Id of the order
+        order_id: String,
+        /// The status the order had before
+        from: crate::api::orders::OrderStatus,
+        /// The status the order was reported in
+        to: crate::api::orders::OrderStatus,
+    },
+
+    /// Error type for a withdrawal that was rejected, before it was sent or by the API
+    #[error("Withdrawal rejected: {0}")]
+    Withdrawal(#[from] crate::api::trading::account::withdrawals::WithdrawalError),
+
+    /// Error type for a download that ended with a different size than the server announced
+    #[error("Download of {id} is {received} bytes, expected {expected}")]
+    IncompleteDownload {
+        /// Id of the downloaded document
+        id: String,
+        /// Size the server announced
+        expected: u64,
+        /// Size that was received
+        received: u64,
+    },
+
+    /// Error type for an invalid client configuration, e.g. a malformed base url or API key
+    #[error("Invalid client configuration: {0}")]
+    Config(String),
 
     /// Error type for other errors
     #[error("{0}")]
     Str(String),
 }
 
+impl Error {
+    /// Create an [`Error::Api`] from an error response of the Lemon API
+    pub(crate) fn api(status: StatusCode, error: LemonError) -> Self {
+        Error::Api {
+            status,
+            code: error.error_code,
+            message: error.error_message,
+            time: error.time,
+            mode: error.mode,
+        }
+    }
+
+    /// HTTP status of the response, if the error came from one
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api { status, .. } | Error::Http(status, _) => Some(*status),
+            Error::Reqwest(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// Lemon API error code, if the error came from the Lemon API
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            Error::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Error message of the Lemon API, if the error came from the Lemon API
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Error::Api { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the same request again later might succeed:
+    /// rate limits, backend errors, timeouts and connection errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api { code, status, .. } => {
+                matches!(
+                    code,
+                    ErrorCode::RateLimitExceeded | ErrorCode::InternalError
+                ) || *status == StatusCode::TOO_MANY_REQUESTS
+                    || status.is_server_error()
+            }
+            Error::Http(status, _) => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            Error::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// Whether the API key is missing, unknown or revoked
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            Error::Api { code, .. } => {
+                matches!(code, ErrorCode::Unauthorized | ErrorCode::TokenInvalid)
+            }
+            Error::Http(status, _) => {
+                matches!(*status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the account does not have enough money for the order or withdrawal
+    pub fn is_funds_error(&self) -> bool {
+        use crate::api::trading::account::withdrawals::WithdrawalError;
+
+        matches!(
+            self,
+            Error::Withdrawal(WithdrawalError::InsufficientFunds { .. })
+        ) || matches!(
+            self.code(),
+            Some(ErrorCode::AccountInsufficientFunds | ErrorCode::WithdrawInsufficientFunds)
+        )
+    }
+}
+
 /// Error type for the Lemon API
-#[derive(Deserialize)]
+#[derive(Deserialize, Debug)]
 pub struct LemonError {
     /// The time that the error occurred
     time: DateTime<Utc>,
@@ -46,70 +197,169 @@ pub struct LemonError {
     error_message: String,
 }
 
-/// Error codes for the Lemon API
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum ErrorCode {
+impl LemonError {
+    /// The time that the error occurred
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    /// API mode.
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    /// Status of the error, e.g. "error"
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Lemon API error code
+    pub fn error_code(&self) -> &ErrorCode {
+        &self.error_code
+    }
+
+    /// Error message
+    pub fn error_message(&self) -> &str {
+        &self.error_message
+    }
+}
+
+/// Defines [`ErrorCode`] from a list of variants and the codes the API sends for them,
+/// with a fallback for codes that are not in the list.
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $variant:ident => $code:literal,)*) => {
+        /// Error codes for the Lemon API
+        ///
+        /// Codes added to the API after this crate was released end up in
+        /// [`ErrorCode::Unknown`], so they never hide the error itself.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($(#[$meta])* $variant,)*
+            /// An error code this crate does not know, as sent by the API
+            Unknown(String),
+        }
+
+        impl ErrorCode {
+            /// The error code as sent by the API, e.g. `insufficient_holdings`
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                    ErrorCode::Unknown(code) => code,
+                }
+            }
+        }
+
+        impl From<&str> for ErrorCode {
+            fn from(code: &str) -> Self {
+                match code {
+                    $($code => ErrorCode::$variant,)*
+                    _ => ErrorCode::Unknown(code.to_string()),
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
     /// The API key is not provided in the HTTP header,
     /// cannot be decoded by the backend,
     /// or the API Key does not exist.
-    Unauthorized,
+    Unauthorized => "unauthorized",
     /// The API key is revoked or user is deleted/suspended.
-    TokenInvalid,
+    TokenInvalid => "token_invalid",
     /// The API key has exceeded its rate limit.
     /// Please respect the value of the Retry-After
     /// header before performing another request.
-    RateLimitExceeded,
-    // TODO: Add [xxx_not_found]
+    RateLimitExceeded => "rate_limit_exceeded",
+    /// The requested account could not be found.
+    AccountNotFound => "account_not_found",
+    /// The requested user could not be found.
+    UserNotFound => "user_not_found",
+    /// The requested order could not be found.
+    OrderNotFound => "order_not_found",
+    /// The requested instrument could not be found.
+    InstrumentNotFound => "instrument_not_found",
+    /// The requested venue could not be found.
+    VenueNotFound => "venue_not_found",
+    /// The requested position could not be found.
+    PositionNotFound => "position_not_found",
+    /// The requested withdrawal could not be found.
+    WithdrawalNotFound => "withdrawal_not_found",
+    /// The requested document could not be found.
+    DocumentNotFound => "document_not_found",
+    /// The requested bank statement could not be found.
+    BankStatementNotFound => "bank_statement_not_found",
+    /// The requested API key could not be found.
+    ApiKeyNotFound => "api_key_not_found",
     /// An error occurred in the backend.
     /// This is not your fault.
     /// We will investigate this.
-    InternalError,
+    InternalError => "internal_error",
     /// Same idempotency has been used within current 7 day period.
-    OrderIdempotencyViolation,
+    OrderIdempotencyViolation => "order_idempotency_violation",
     /// Cannot withdraw money because the PIN is not provided in the request (money only).
-    PinMissing,
+    PinMissing => "pin_missing",
     /// Cannot withdraw money because the PIN is not set (money only)
-    PinNotSet,
+    PinNotSet => "pin_not_set",
     /// Cannot withdraw money because the pin verification failed
-    PinInvalid,
+    PinInvalid => "pin_invalid",
     /// Cannot withdraw money because there are insufficient funds on the account
-    WithdrawInsufficientFunds,
+    WithdrawInsufficientFunds => "withdraw_insufficient_funds",
     /// Cannot withdraw money because the daily payout limit is exceeded
-    WithdrawLimitExceeded,
+    WithdrawLimitExceeded => "withdraw_limit_exceeded",
     /// Cannot withdraw money because the maximal daily request limit is exceeded
-    WithdrawRequestLimitExceeded,
+    WithdrawRequestLimitExceeded => "withdraw_request_limit_exceeded",
     /// Cannot update address_country when trading is enabled or user is onboarded
-    ForbiddenInCurrentState,
+    ForbiddenInCurrentState => "forbidden_in_current_state",
     /// Cannot update trading plan to basic/pro
-    PlanNotAllowed,
+    PlanNotAllowed => "plan_not_allowed",
     ///    insufficient instrument holdings [on order sell]
-    InsufficientHoldings,
+    InsufficientHoldings => "insufficient_holdings",
     ///    cannot place order if one expires before market opens again
-    OrderExpirationDateInvalid,
+    OrderExpirationDateInvalid => "order_expiration_date_invalid",
     ///    cannot place/activate buy order if estimated total price is greater than 25k Euro
-    OrderTotalPriceLimitExceeded,
+    OrderTotalPriceLimitExceeded => "order_total_price_limit_exceeded",
     ///cannot place order in ALLDAY for MONEY env
-    ForbiddenForVenue,
+    ForbiddenForVenue => "forbidden_for_venue",
     ///    cannot place order if trading is not enabled
-    TradingDisabled,
+    TradingDisabled => "trading_disabled",
     ///maximum daily amount of orders reached
-    OrderLimitExceeded,
+    OrderLimitExceeded => "order_limit_exceeded",
     ///failed to place order if instrument is not tradable
-    InstrumentNotTradable,
+    InstrumentNotTradable => "instrument_not_tradable",
     ///cannot place/activate buy order because of insufficient account funds
-    AccountInsufficientFunds,
+    AccountInsufficientFunds => "account_insufficient_funds",
     ///cannot place/activate order because trading is blocked globally
-    TradingBlocked,
+    TradingBlocked => "trading_blocked",
     ///cannot activate order if its status != inactive
-    OrderNotInactive,
+    OrderNotInactive => "order_not_inactive",
     /// cannot delete order if its not in cancelling/cancelled/expired/executed/rejected state
-    OrderNotTerminated,
+    OrderNotTerminated => "order_not_terminated",
+}
+
+impl ErrorCode {
+    /// Whether the code reports that a requested resource does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.as_str().ends_with("_not_found")
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Ok(ErrorCode::from(code.as_str()))
+    }
 }
 
 impl std::fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.as_str())
     }
 }
 
@@ -122,3 +372,23 @@ impl std::fmt::Display for LemonError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorCode;
+
+    #[test]
+    fn test_error_code_round_trip() {
+        let code: ErrorCode = serde_json::from_str(r#""order_not_found""#).unwrap();
+        assert_eq!(code, ErrorCode::OrderNotFound);
+        assert!(code.is_not_found());
+        assert_eq!(
+            serde_json::to_string(&code).unwrap(),
+            r#""order_not_found""#
+        );
+
+        let code: ErrorCode = serde_json::from_str(r#""brand_new_code""#).unwrap();
+        assert_eq!(code, ErrorCode::Unknown("brand_new_code".to_string()));
+        assert_eq!(code.to_string(), "brand_new_code");
+    }
+}
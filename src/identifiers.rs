@@ -0,0 +1,345 @@
+//! Validated identifiers of instruments and venues
+//!
+//! [`Isin`], [`Wkn`] and [`Mic`] are checked when they are parsed, so a typo fails locally
+//! instead of as a backend error. Lowercase input is accepted and stored in uppercase.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Error returned when a string is not a valid identifier
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseIdentifierError {
+    /// The identifier has the wrong number of characters
+    #[error("{kind} {input:?} must be {expected} characters long")]
+    InvalidLength {
+        /// The kind of identifier, e.g. "ISIN"
+        kind: &'static str,
+        /// The string that failed to parse
+        input: String,
+        /// The number of characters the identifier needs to have
+        expected: usize,
+    },
+    /// The identifier is shorter or longer than identifiers of its kind can be
+    #[error("{kind} {input:?} must be {min} to {max} characters long")]
+    LengthOutOfRange {
+        /// The kind of identifier, e.g. "MIC"
+        kind: &'static str,
+        /// The string that failed to parse
+        input: String,
+        /// The least number of characters
+        min: usize,
+        /// The most number of characters
+        max: usize,
+    },
+    /// The identifier contains a character it may not contain at that position
+    #[error("{kind} {input:?} contains the invalid character {character:?}")]
+    InvalidCharacter {
+        /// The kind of identifier, e.g. "ISIN"
+        kind: &'static str,
+        /// The string that failed to parse
+        input: String,
+        /// The first invalid character
+        character: char,
+    },
+    /// The check digit of an ISIN does not match the rest of it
+    #[error("ISIN {input:?} has check digit {found}, expected {expected}")]
+    InvalidCheckDigit {
+        /// The string that failed to parse
+        input: String,
+        /// The check digit computed from the first 11 characters
+        expected: char,
+        /// The last character of the input
+        found: char,
+    },
+}
+
+/// Defines a string identifier that is validated by `$validate`
+macro_rules! identifier {
+    ($(#[$meta:meta])* $name:ident, $validate:path) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(String);
+
+        impl $name {
+            /// The identifier as a string
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIdentifierError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.to_ascii_uppercase();
+                $validate(&s)?;
+                Ok(Self(s))
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = ParseIdentifierError;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = ParseIdentifierError;
+
+            fn try_from(s: &str) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+identifier! {
+    /// International Securities Identification Number (ISO 6166), e.g. `US0378331005`
+    ///
+    /// Two letters for the country, nine alphanumeric characters, and a check digit.
+    ///
+    /// ```
+    /// use septoria::identifiers::Isin;
+    ///
+    /// let isin: Isin = "US0378331005".parse().unwrap();
+    /// assert_eq!(isin.country_code(), "US");
+    /// assert!("US0378331006".parse::<Isin>().is_err());
+    /// ```
+    Isin, validate_isin
+}
+
+identifier! {
+    /// Wertpapierkennnummer, the German securities identification number, e.g. `865985`
+    ///
+    /// Six digits or uppercase letters, except `I` and `O`.
+    Wkn, validate_wkn
+}
+
+identifier! {
+    /// Market Identifier Code of a venue, e.g. `XMUN`
+    ///
+    /// One to six digits or uppercase letters. Besides the four character codes of ISO 10383,
+    /// the API has its own venues with longer codes, e.g. `LMBPX` and `ALLDAY`.
+    Mic, validate_mic
+}
+
+impl Isin {
+    /// The ISO 3166 country code the ISIN starts with
+    pub fn country_code(&self) -> &str {
+        &self.0[..2]
+    }
+}
+
+/// The number of characters a MIC can have
+const MIC_LENGTH: std::ops::RangeInclusive<usize> = 1..=6;
+
+/// Check that `s` has `expected` characters that all satisfy `valid`
+fn validate_chars(
+    kind: &'static str,
+    s: &str,
+    expected: usize,
+    valid: impl Fn(usize, char) -> bool,
+) -> Result<(), ParseIdentifierError> {
+    if s.chars().count() != expected {
+        return Err(ParseIdentifierError::InvalidLength {
+            kind,
+            input: s.to_string(),
+            expected,
+        });
+    }
+    match s.chars().enumerate().find(|&(i, c)| !valid(i, c)) {
+        Some((_, character)) => Err(ParseIdentifierError::InvalidCharacter {
+            kind,
+            input: s.to_string(),
+            character,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Validate an uppercase ISIN, including its check digit
+fn validate_isin(s: &str) -> Result<(), ParseIdentifierError> {
+    validate_chars("ISIN", s, 12, |i, c| match i {
+        0 | 1 => c.is_ascii_uppercase(),
+        11 => c.is_ascii_digit(),
+        _ => c.is_ascii_digit() || c.is_ascii_uppercase(),
+    })?;
+    let expected = isin_check_digit(&s[..11]);
+    let found = s.chars().last().unwrap_or_default();
+    if found != expected {
+        return Err(ParseIdentifierError::InvalidCheckDigit {
+            input: s.to_string(),
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// The check digit of the first 11 characters of an ISIN.
+///
+/// Letters are replaced by two digits (`A` = 10 to `Z` = 35), then the Luhn algorithm is
+/// applied to the resulting digits.
+fn isin_check_digit(s: &str) -> char {
+    let digits: Vec<u32> = s
+        .chars()
+        .flat_map(|c| {
+            let value = c.to_digit(36).unwrap_or_default();
+            if value < 10 {
+                vec![value]
+            } else {
+                vec![value / 10, value % 10]
+            }
+        })
+        .collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            0 => {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            }
+            _ => digit,
+        })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+}
+
+/// Validate an uppercase WKN
+fn validate_wkn(s: &str) -> Result<(), ParseIdentifierError> {
+    validate_chars("WKN", s, 6, |_, c| {
+        c.is_ascii_digit() || (c.is_ascii_uppercase() && c != 'I' && c != 'O')
+    })
+}
+
+/// Validate an uppercase MIC
+fn validate_mic(s: &str) -> Result<(), ParseIdentifierError> {
+    let length = s.chars().count();
+    if !(MIC_LENGTH.contains(&length)) {
+        return Err(ParseIdentifierError::LengthOutOfRange {
+            kind: "MIC",
+            input: s.to_string(),
+            min: *MIC_LENGTH.start(),
+            max: *MIC_LENGTH.end(),
+        });
+    }
+    validate_chars("MIC", s, length, |_, c| {
+        c.is_ascii_digit() || c.is_ascii_uppercase()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Isin, Mic, ParseIdentifierError, Wkn};
+    use crate::api::market_data::venues::VenueData;
+
+    #[test]
+    fn test_isin_check_digit() {
+        for isin in [
+            "US0378331005",
+            "DE0007164600",
+            "NO0010234552",
+            "us88160r1014",
+        ] {
+            assert!(isin.parse::<Isin>().is_ok(), "{isin}");
+        }
+        assert_eq!(
+            "US0378331006".parse::<Isin>(),
+            Err(ParseIdentifierError::InvalidCheckDigit {
+                input: "US0378331006".to_string(),
+                expected: '5',
+                found: '6',
+            })
+        );
+        assert!(matches!(
+            "US037833100".parse::<Isin>(),
+            Err(ParseIdentifierError::InvalidLength { expected: 12, .. })
+        ));
+        assert!(matches!(
+            "1S0378331005".parse::<Isin>(),
+            Err(ParseIdentifierError::InvalidCharacter { character: '1', .. })
+        ));
+    }
+
+    #[test]
+    fn test_wkn_and_mic() {
+        assert_eq!("a0hgrf".parse::<Wkn>().unwrap(), "A0HGRF");
+        assert!("86598O".parse::<Wkn>().is_err());
+        assert_eq!("XMUN".parse::<Mic>().unwrap().as_str(), "XMUN");
+        assert_eq!("lmbpx".parse::<Mic>().unwrap(), "LMBPX");
+        assert_eq!("ALLDAY".parse::<Mic>().unwrap(), "ALLDAY");
+        assert!(matches!(
+            "".parse::<Mic>(),
+            Err(ParseIdentifierError::LengthOutOfRange { min: 1, max: 6, .. })
+        ));
+        assert!("XMUNXMU".parse::<Mic>().is_err());
+        assert!("XM-N".parse::<Mic>().is_err());
+    }
+
+    #[test]
+    fn test_venue_codes_of_the_api() {
+        for mic in ["LMBPX", "ALLDAY"] {
+            let venue = format!(
+                r#"{{
+                    "name": "Lemon Markets Venue",
+                    "title": "{mic}",
+                    "mic": "{mic}",
+                    "is_open": true,
+                    "opening_hours": {{
+                        "start": "08:00",
+                        "end": "22:00",
+                        "timezone": "Europe/Berlin"
+                    }},
+                    "opening_days": ["2022-03-04"]
+                }}"#
+            );
+            let venue: VenueData = serde_json::from_str(&venue).unwrap();
+            assert_eq!(venue.mic, mic);
+        }
+    }
+
+    #[test]
+    fn test_serde_validates() {
+        let isin: Isin = serde_json::from_str("\"US0378331005\"").unwrap();
+        assert_eq!(serde_json::to_string(&isin).unwrap(), "\"US0378331005\"");
+        assert!(serde_json::from_str::<Isin>("\"US0378331006\"").is_err());
+    }
+}
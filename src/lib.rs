@@ -2,11 +2,34 @@
 
 #![deny(missing_docs)]
 pub mod api;
+/// Builders to configure the endpoint and transport settings of the clients
+pub mod builder;
+/// Trading hours of venues, interpreted in their timezone
+pub mod calendar;
+/// Record and replay HTTP interactions
+pub mod cassette;
 /// API client for the Lemon market trading API
 pub mod client;
 /// Data client for the Lemon market data API
 pub mod data_client;
 /// Error type for the Lemon market_data API
 pub mod error;
-/// Module for utilities
-mod util;
+/// Validated identifiers of instruments and venues
+pub mod identifiers;
+/// Offline mock of the API for tests
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+/// Fixed-point amounts of money, as used by the API
+pub mod money;
+/// Iterators over paginated endpoints
+pub mod pagination;
+/// Client-side rate limiting
+pub mod rate_limit;
+/// Request core shared by the blocking and async clients
+mod request;
+/// Retry policy for failed requests
+pub mod retry;
+/// Pre-trade risk checks for orders
+pub mod risk;
+/// Pluggable HTTP transports for the clients
+pub mod transport;
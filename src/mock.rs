@@ -0,0 +1,1170 @@
+//! Offline mock of the lemon.markets API
+//!
+//! [`MockServer`] serves the trading and market data endpoints on localhost, backed by an
+//! in-memory [`MockState`]. Point a client at it through its base url, e.g. with
+//! [`MockServer::trading_client`], to test code without API keys or network access.
+//! Specific [`ErrorCode`]s can be injected with [`MockServer::fail_next`].
+//!
+//! The `septoria-mock` binary serves the same mock for code outside of Rust tests.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use chrono::prelude::*;
+use reqwest::{Method, StatusCode, Url};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::api::market_data::instruments::{InstrumentInfo, InstrumentType, InstrumentVenue};
+use crate::api::market_data::venues::{OpeningHours, VenueData};
+use crate::api::orders::{
+    ActivateOrder, InvalidOrder, OrderRequest, OrderResults, OrderStatus, OrderType,
+};
+use crate::api::trading::account::documents::{
+    BankStatement, BankStatementType, Document, DocumentCategory,
+};
+use crate::api::trading::account::withdrawals::Withdrawal;
+use crate::api::trading::account::{AccountResults, Plan};
+use crate::api::trading::positions::performance::PositionPerformance;
+use crate::api::trading::positions::statements::{Statement, StatementType};
+use crate::api::trading::positions::Position;
+use crate::api::Mode;
+use crate::client::TradingClient;
+use crate::data_client::DataClient;
+use crate::error::ErrorCode;
+use crate::identifiers::{Isin, Wkn};
+use crate::money::{Amount, Price};
+
+/// API key the clients of [`MockServer::trading_client`] and [`MockServer::data_client`] use
+pub static MOCK_API_KEY: &str = "mock_api_key";
+
+/// Page size of list endpoints when no `limit` is given
+const DEFAULT_LIMIT: usize = 100;
+
+/// A lemon.markets API served on localhost
+///
+/// The server runs on a background thread until it is dropped.
+pub struct MockServer {
+    /// Base url of the API, e.g. `http://127.0.0.1:34567/v1`
+    url: String,
+    /// State shared with the server thread
+    state: Arc<Mutex<MockState>>,
+    /// The HTTP server, shared with the server thread
+    server: Arc<tiny_http::Server>,
+    /// Thread answering the requests
+    thread: Option<JoinHandle<()>>,
+}
+
+/// In-memory state of a [`MockServer`]
+///
+#[derive(Debug)]
+pub struct MockState {
+    /// The account of the API key
+    pub account: AccountResults,
+    /// PIN needed to activate orders and submit withdrawals
+    pub pin: i64,
+    /// Orders, in the order they were placed
+    pub orders: Vec<OrderResults>,
+    /// Positions of the account
+    pub positions: Vec<Position>,
+    /// Performance of past and current positions
+    pub performance: Vec<PositionPerformance>,
+    /// Change events of the positions
+    pub statements: Vec<Statement>,
+    /// Bookings on the cash account, in the order they were made
+    pub bank_statements: Vec<BankStatement>,
+    /// Withdrawals, in the order they were submitted
+    pub withdrawals: Vec<Withdrawal>,
+    /// Documents of the account
+    pub documents: Vec<Document>,
+    /// Content of the documents, by id
+    pub document_contents: HashMap<String, Vec<u8>>,
+    /// Instruments that can be searched and traded
+    pub instruments: Vec<InstrumentInfo>,
+    /// Venues instruments are traded at
+    pub venues: Vec<VenueData>,
+    /// Price of a single share of an instrument, by ISIN
+    pub prices: HashMap<Isin, Price>,
+    /// Errors to answer the next matching requests with
+    errors: Vec<InjectedError>,
+    /// Counter for the ids of new orders, withdrawals and statements
+    next_id: u64,
+}
+
+/// An error a request is answered with instead of being processed
+#[derive(Debug)]
+struct InjectedError {
+    /// HTTP method the request needs to have
+    method: Method,
+    /// Path the request needs to have, relative to the base url and without slashes around it
+    path: String,
+    /// Error code to answer with
+    code: ErrorCode,
+}
+
+/// A request the mock does not process successfully
+#[derive(Debug)]
+struct Failure {
+    /// HTTP status of the response
+    status: StatusCode,
+    /// Error code of the response
+    code: ErrorCode,
+}
+
+/// A response of the mock
+#[derive(Debug)]
+struct Reply {
+    /// HTTP status of the response
+    status: StatusCode,
+    /// `Content-Type` of the body
+    content_type: &'static str,
+    /// `Content-Range` of a partial download
+    content_range: Option<String>,
+    /// The raw body
+    body: Vec<u8>,
+}
+
+impl Reply {
+    /// A successful json response
+    fn json(body: Value) -> Self {
+        Self {
+            status: StatusCode::OK,
+            content_type: "application/json",
+            content_range: None,
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    /// A successful response with (a part of) a PDF document
+    fn pdf(status: StatusCode, content_range: Option<String>, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type: "application/pdf",
+            content_range,
+            body,
+        }
+    }
+}
+
+impl From<ErrorCode> for Failure {
+    fn from(code: ErrorCode) -> Self {
+        let status = match code {
+            ErrorCode::Unauthorized | ErrorCode::TokenInvalid => StatusCode::UNAUTHORIZED,
+            ErrorCode::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ref code if code.is_not_found() => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self { status, code }
+    }
+}
+
+impl Failure {
+    /// A request that does not match any endpoint
+    fn route_not_found() -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            code: ErrorCode::from("not_found"),
+        }
+    }
+
+    /// A request with a body or query the endpoint cannot process
+    fn validation_error() -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code: ErrorCode::from("validation_error"),
+        }
+    }
+}
+
+impl MockServer {
+    /// Serve the mock on a free port of localhost, with the default [`MockState`]
+    pub fn start() -> io::Result<Self> {
+        Self::bind("127.0.0.1:0")
+    }
+
+    /// Serve the mock on the given address, with the default [`MockState`]
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        Self::with_state(addr, MockState::default())
+    }
+
+    /// Serve the mock on the given address, with the given state
+    pub fn with_state(addr: &str, state: MockState) -> io::Result<Self> {
+        let server = Arc::new(tiny_http::Server::http(addr).map_err(io::Error::other)?);
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("the mock only listens on IP addresses"))?;
+        let url = format!("http://{}/v1", addr);
+        let state = Arc::new(Mutex::new(state));
+
+        let thread = {
+            let server = server.clone();
+            let state = state.clone();
+            let url = url.clone();
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(&state, &url, request);
+                }
+            })
+        };
+
+        Ok(Self {
+            url,
+            state,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    /// Base url to configure clients with, e.g. through
+    /// [`ClientBuilder::base_url`](crate::builder::ClientBuilder::base_url)
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// A trading client for this server
+    pub fn trading_client(&self) -> TradingClient {
+        TradingClient::new(MOCK_API_KEY.to_string(), &self.url)
+    }
+
+    /// A market data client for this server
+    pub fn data_client(&self) -> DataClient {
+        DataClient::builder(MOCK_API_KEY)
+            .base_url(&self.url)
+            .build()
+            .expect("the mock url is valid")
+    }
+
+    /// Lock the state to inspect or change it
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        lock(&self.state)
+    }
+
+    /// Answer the next `method` request to `path` with `code` instead of processing it.
+    ///
+    /// `path` is relative to the base url, e.g. `orders/` or `account/withdrawals`. The HTTP
+    /// status is the one the API uses for the code, e.g. 404 for the `*_not_found` codes.
+    pub fn fail_next(&self, method: Method, path: &str, code: ErrorCode) {
+        self.state().errors.push(InjectedError {
+            method,
+            path: path.trim_matches('/').to_string(),
+            code,
+        });
+    }
+
+    /// Block the current thread while the server is running
+    pub fn join(mut self) {
+        if let Some(thread) = self.thread.take() {
+            // A panic on the server thread already shows up in the failed requests
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Default for MockState {
+    /// A paper account with 10,000€ to invest, and a few instruments traded at gettex
+    fn default() -> Self {
+        let today = Utc::now().date_naive();
+        let cash = Amount::from_euros(10_000).expect("fits");
+        let created_at = Utc.with_ymd_and_hms(2022, 1, 3, 9, 0, 0).unwrap();
+        let venue = VenueData {
+            name: "Börse München - Gettex".to_string(),
+            title: "Gettex".to_string(),
+            mic: "XMUN".parse().expect("valid MIC"),
+            is_open: true,
+            opening_hours: OpeningHours {
+                start: "08:00".to_string(),
+                end: "22:00".to_string(),
+                timezone: "Europe/Berlin".to_string(),
+            },
+            opening_days: today.iter_days().take(3).collect(),
+        };
+        let isin = |isin: &str| -> Isin { isin.parse().expect("valid ISIN") };
+        let instrument = |isin: &str, wkn: &str, name: &str, symbol: &str| InstrumentInfo {
+            isin: Some(isin.parse().expect("valid ISIN")),
+            wkn: Some(wkn.parse().expect("valid WKN")),
+            name: Some(name.to_string()),
+            title: Some(name.to_string()),
+            symbol: Some(symbol.to_string()),
+            instrument_type: Some(InstrumentType::Stock),
+            venues: Some(vec![InstrumentVenue {
+                name: venue.name.clone(),
+                title: venue.title.clone(),
+                mic: venue.mic.clone(),
+                is_open: true,
+                tradable: true,
+                currency: "EUR".to_string(),
+            }]),
+        };
+
+        Self {
+            account: AccountResults {
+                created_at,
+                account_id: "acc_mock".to_string(),
+                firstname: "Mock".to_string(),
+                lastname: Some("Trader".to_string()),
+                email: "mock@example.com".to_string(),
+                phone: None,
+                address: None,
+                address_country: None,
+                billing_address: None,
+                billing_email: None,
+                billing_name: None,
+                billing_vat: None,
+                mode: Mode::Paper,
+                deposit_id: None,
+                client_id: None,
+                account_number: None,
+                iban_brokerage: None,
+                iban_origin: None,
+                bank_name_origin: None,
+                balance: cash,
+                cash_to_invest: cash,
+                cash_to_withdraw: cash,
+                amount_bought_intraday: Amount::ZERO,
+                amount_sold_intraday: Amount::ZERO,
+                amount_open_orders: Amount::ZERO,
+                amount_open_withdrawals: Amount::ZERO,
+                amount_estimate_taxes: Amount::ZERO,
+                approved_at: None,
+                trading_plan: Plan::Investor,
+                data_plan: Plan::Investor,
+                tax_allowance: None,
+                tax_allowance_start: None,
+                tax_allowance_end: None,
+            },
+            pin: 1234,
+            orders: vec![],
+            positions: vec![],
+            performance: vec![],
+            statements: vec![],
+            bank_statements: vec![BankStatement {
+                id: "bst_mock000000".to_string(),
+                account_id: "acc_mock".to_string(),
+                statement_type: BankStatementType::PayIn,
+                date: created_at.date_naive(),
+                amount: cash,
+                isin: None,
+                isin_title: None,
+                created_at,
+                quantity: None,
+            }],
+            withdrawals: vec![],
+            documents: vec![Document {
+                id: "doc_mock000000".to_string(),
+                name: "account_opening.pdf".to_string(),
+                created_at,
+                category: DocumentCategory::Contract,
+                viewed_first_at: None,
+                viewed_last_at: None,
+            }],
+            document_contents: HashMap::from([(
+                "doc_mock000000".to_string(),
+                b"%PDF-1.4\n% Account opening documents of the mock account\n%%EOF\n".to_vec(),
+            )]),
+            instruments: vec![
+                instrument("US0378331005", "865985", "APPLE INC.", "AAPL"),
+                instrument("DE0007164600", "716460", "SAP SE", "SAP"),
+            ],
+            venues: vec![venue],
+            prices: HashMap::from([
+                (isin("US0378331005"), Price::from_raw(1_500_000)),
+                (isin("DE0007164600"), Price::from_raw(1_200_000)),
+            ]),
+            errors: vec![],
+            next_id: 1,
+        }
+    }
+}
+
+impl MockState {
+    /// A new id with the given prefix, e.g. `ord_`
+    fn next_id(&mut self, prefix: &str) -> String {
+        let id = format!("{}mock{:06}", prefix, self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Take the injected error for the request, if there is one
+    fn take_error(&mut self, method: &Method, path: &str) -> Option<ErrorCode> {
+        let index = self
+            .errors
+            .iter()
+            .position(|error| error.method == *method && error.path == path)?;
+        Some(self.errors.remove(index).code)
+    }
+
+    /// Process a request, and return the body of the response
+    fn handle(
+        &mut self,
+        base_url: &str,
+        method: &Method,
+        path: &str,
+        query: &HashMap<String, String>,
+        body: &str,
+    ) -> Result<Value, Failure> {
+        if let Some(code) = self.take_error(method, path) {
+            return Err(code.into());
+        }
+        let list = |items: Vec<Value>| paginate(base_url, path, query, items);
+        let segments: Vec<&str> = path.split('/').collect();
+        match (method.as_str(), segments.as_slice()) {
+            ("GET", ["account"]) => Ok(results(Mode::Paper, &self.account)),
+            ("PUT", ["account"]) => self.update_account(parse(body)?),
+            ("GET", ["account", "bankstatements"]) => list(self.bank_statements(query)),
+            ("GET", ["account", "documents"]) => list(values(&self.documents)),
+            ("GET", ["account", "withdrawals"]) => list(values(&self.withdrawals)),
+            ("POST", ["account", "withdrawals"]) => self.post_withdrawal(parse(body)?),
+            ("GET", ["account", "withdrawals", id]) => {
+                Ok(results(Mode::Paper, self.withdrawal(id)?))
+            }
+            ("GET", ["orders"]) => list(self.orders(query)),
+            ("POST", ["orders"]) => self.place_order(parse(body)?),
+            ("GET", ["orders", id]) => Ok(results(Mode::Paper, self.order(id)?)),
+            ("DELETE", ["orders", id]) => self.delete_order(id),
+            ("POST", ["orders", id, "activate"]) => self.activate_order(id, parse(body)?),
+            ("GET", ["positions"]) => list(self.positions(query)),
+            ("GET", ["positions", "performance"]) => list(self.performance(query)),
+            ("GET", ["positions", "statements"]) => list(self.statements(query)),
+            ("GET", ["instruments"]) => list(self.instruments(query)),
+            ("GET", ["venues"]) => {
+                list(values(self.venues.iter().filter(|venue| {
+                    query.get("mic").is_none_or(|mic| venue.mic.as_str() == mic)
+                })))
+            }
+            ("GET", ["quotes", "latest"]) => list(self.quotes(query)),
+            _ => Err(Failure::route_not_found()),
+        }
+    }
+
+    /// The latest quotes of the instruments in the `isin` filter, at the venue in the `mic`
+    /// filter. Bid and ask are both the price of the instrument
+    fn quotes(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let mic = query.get("mic").map_or("XMUN", String::as_str);
+        let Some(isins) = query.get("isin") else {
+            return vec![];
+        };
+        isins
+            .split(',')
+            .filter_map(|isin| {
+                let (isin, price) = self
+                    .prices
+                    .iter()
+                    .find(|(known, _)| known.as_str() == isin)?;
+                Some(json!({
+                    "isin": isin,
+                    "mic": mic,
+                    "b": price,
+                    "a": price,
+                    "b_v": 1000,
+                    "a_v": 1000,
+                    "t": Utc::now(),
+                }))
+            })
+            .collect()
+    }
+
+    /// Process a request for the content of a document, starting at the byte the `Range`
+    /// header asks for
+    fn download(&mut self, path: &str, id: &str, range: Option<&str>) -> Result<Reply, Failure> {
+        if let Some(code) = self.take_error(&Method::GET, path) {
+            return Err(code.into());
+        }
+        let content = self
+            .document_contents
+            .get(id)
+            .ok_or(ErrorCode::DocumentNotFound)?;
+        let size = content.len();
+        let start = match range.and_then(|range| range.strip_prefix("bytes=")) {
+            Some(range) => range
+                .trim_end_matches('-')
+                .parse::<usize>()
+                .map_err(|_| Failure::validation_error())?,
+            None => 0,
+        };
+        Ok(match start {
+            0 => Reply::pdf(StatusCode::OK, None, content.clone()),
+            start if start >= size => Reply::pdf(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                Some(format!("bytes */{}", size)),
+                vec![],
+            ),
+            start => Reply::pdf(
+                StatusCode::PARTIAL_CONTENT,
+                Some(format!("bytes {}-{}/{}", start, size - 1, size)),
+                content[start..].to_vec(),
+            ),
+        })
+    }
+
+    /// The order with the given id
+    fn order(&self, id: &str) -> Result<&OrderResults, Failure> {
+        self.orders
+            .iter()
+            .find(|order| order.id == id)
+            .ok_or_else(|| ErrorCode::OrderNotFound.into())
+    }
+
+    /// Orders matching the filters of an [`OrderQuery`](crate::api::orders::OrderQuery)
+    fn orders(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let time = |key: &str| {
+            query
+                .get(key)
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        };
+        let (from, to) = (time("from"), time("to"));
+        let mut orders: Vec<Value> = values(self.orders.iter().filter(|order| {
+            from.is_none_or(|from| order.created_at >= from)
+                && to.is_none_or(|to| order.created_at <= to)
+        }));
+        // The remaining filters compare against the serialized field of the same name
+        orders.retain(|order| {
+            ["isin", "side", "status", "type", "key_creation_id"]
+                .iter()
+                .all(|key| query.get(*key).is_none_or(|value| order[*key] == *value))
+        });
+        if query
+            .get("sorting")
+            .is_some_and(|sorting| sorting == "desc")
+        {
+            orders.reverse();
+        }
+        orders
+    }
+
+    /// Positions matching the `isin` filter of a
+    /// [`PositionQuery`](crate::api::trading::positions::PositionQuery)
+    fn positions(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        values(self.positions.iter().filter(|position| {
+            query
+                .get("isin")
+                .is_none_or(|isin| position.isin.as_str() == isin)
+        }))
+    }
+
+    /// Position statements matching the filters of a
+    /// [`StatementQuery`](crate::api::trading::positions::statements::StatementQuery)
+    fn statements(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let types: Option<Vec<&str>> = query.get("types").map(|types| types.split(',').collect());
+        let mut statements = values(self.statements.iter().filter(|statement| {
+            in_range(query, Some(statement.created_at))
+                && query
+                    .get("isin")
+                    .is_none_or(|isin| statement.isin.as_str() == isin)
+                && types
+                    .as_ref()
+                    .is_none_or(|types| types.contains(&statement.statement_type.as_str()))
+        }));
+        sort(query, &mut statements);
+        statements
+    }
+
+    /// Position performances matching the filters of a
+    /// [`PerformanceQuery`](crate::api::trading::positions::performance::PerformanceQuery)
+    fn performance(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let mut performance = values(self.performance.iter().filter(|performance| {
+            in_range(query, performance.opened_at)
+                && query
+                    .get("isin")
+                    .is_none_or(|isin| performance.isin.as_str() == isin)
+        }));
+        sort(query, &mut performance);
+        performance
+    }
+
+    /// Bank statements matching the filters of a
+    /// [`BankStatementQuery`](crate::api::trading::account::documents::BankStatementQuery)
+    fn bank_statements(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let date = |key: &str| {
+            query
+                .get(key)
+                .and_then(|date| date.parse::<NaiveDate>().ok())
+        };
+        let (from, to) = (date("from"), date("to"));
+        let mut statements = values(self.bank_statements.iter().filter(|statement| {
+            from.is_none_or(|from| statement.date >= from)
+                && to.is_none_or(|to| statement.date <= to)
+                && query
+                    .get("type")
+                    .is_none_or(|t| statement.statement_type.as_str() == t)
+        }));
+        if query
+            .get("sorting")
+            .is_some_and(|sorting| sorting == "desc")
+        {
+            statements.reverse();
+        }
+        statements
+    }
+
+    /// Book money on the cash account
+    fn book(
+        &mut self,
+        statement_type: BankStatementType,
+        amount: Amount,
+        order: Option<(&Isin, &str, i64)>,
+    ) {
+        let statement = BankStatement {
+            id: self.next_id("bst_"),
+            account_id: self.account.account_id.clone(),
+            statement_type,
+            date: Utc::now().date_naive(),
+            amount,
+            isin: order.map(|(isin, _, _)| isin.clone()),
+            isin_title: order.map(|(_, title, _)| title.to_string()),
+            created_at: Utc::now(),
+            quantity: order.map(|(_, _, quantity)| quantity),
+        };
+        self.bank_statements.push(statement);
+    }
+
+    /// Instruments matching the `isin`, `search` and `type` filters
+    fn instruments(&self, query: &HashMap<String, String>) -> Vec<Value> {
+        let isins: Option<Vec<&str>> = query.get("isin").map(|isin| isin.split(',').collect());
+        let search = query.get("search").map(|search| search.to_lowercase());
+        values(self.instruments.iter().filter(|instrument| {
+            let isin = instrument.isin.as_ref().map_or("", Isin::as_str);
+            let matches_isin = isins.as_ref().is_none_or(|isins| isins.contains(&isin));
+            let matches_type = query.get("type").is_none_or(|t| {
+                instrument.instrument_type == Some(InstrumentType::from(t.as_str()))
+            });
+            let matches_search = search.as_ref().is_none_or(|search| {
+                [
+                    instrument.isin.as_ref().map(Isin::as_str),
+                    instrument.wkn.as_ref().map(Wkn::as_str),
+                    instrument.name.as_deref(),
+                    instrument.title.as_deref(),
+                    instrument.symbol.as_deref(),
+                ]
+                .iter()
+                .any(|field| field.is_some_and(|field| field.to_lowercase().contains(search)))
+            });
+            matches_isin && matches_type && matches_search
+        }))
+    }
+
+    /// Place an inactive order, if the instrument and venue exist and the account can pay
+    fn place_order(&mut self, order: OrderRequest) -> Result<Value, Failure> {
+        if let Err(error) = order.validate() {
+            return Err(match error {
+                InvalidOrder::Expiry { .. } => ErrorCode::OrderExpirationDateInvalid.into(),
+                _ => Failure::validation_error(),
+            });
+        }
+        if let Some(idempotency) = &order.idempotency {
+            if self
+                .orders
+                .iter()
+                .any(|o| o.idempotency.as_ref() == Some(idempotency))
+            {
+                return Err(ErrorCode::OrderIdempotencyViolation.into());
+            }
+        }
+        let price = *self
+            .prices
+            .get(&order.isin)
+            .ok_or(ErrorCode::InstrumentNotFound)?;
+        if let Some(venue) = &order.venue {
+            if !self.venues.iter().any(|v| v.mic == *venue) {
+                return Err(ErrorCode::VenueNotFound.into());
+            }
+        }
+        let total = price
+            .checked_total(order.quantity)
+            .ok_or_else(Failure::validation_error)?;
+        match order.side {
+            OrderType::Buy if total > self.account.cash_to_invest => {
+                return Err(ErrorCode::AccountInsufficientFunds.into());
+            }
+            OrderType::Sell if self.holdings(&order.isin) < order.quantity => {
+                return Err(ErrorCode::InsufficientHoldings.into());
+            }
+            _ => {}
+        }
+
+        let kind = order.kind();
+        let placed = OrderResults {
+            created_at: Utc::now(),
+            id: self.next_id("ord_"),
+            status: OrderStatus::Inactive,
+            regulatory_information: None,
+            isin: Some(order.isin),
+            expires_at: order.expires_at.map(|date| date.to_string()),
+            side: Some(order.side),
+            kind: Some(kind),
+            quantity: Some(order.quantity),
+            stop_price: order.stop_price,
+            limit_price: order.limit_price,
+            venue: order.venue,
+            estimated_price: Some(total),
+            notes: order.notes,
+            idempotency: order.idempotency,
+            charge: Some(Amount::ZERO),
+            chargeable_at: None,
+            key_creation_id: None,
+        };
+        let response = results(Mode::Paper, &placed);
+        self.orders.push(placed);
+        Ok(response)
+    }
+
+    /// Activate an inactive order. The mock executes it right away.
+    fn activate_order(&mut self, id: &str, activation: ActivateOrder) -> Result<Value, Failure> {
+        if activation.pin != self.pin {
+            return Err(ErrorCode::PinInvalid.into());
+        }
+        let index = self
+            .orders
+            .iter()
+            .position(|order| order.id == id)
+            .ok_or(ErrorCode::OrderNotFound)?;
+        if self.orders[index].status != OrderStatus::Inactive {
+            return Err(ErrorCode::OrderNotInactive.into());
+        }
+        self.orders[index].status = OrderStatus::Executed;
+        self.execute(index);
+        Ok(ok(Mode::Paper))
+    }
+
+    /// Cancel an order that has not been executed yet
+    fn delete_order(&mut self, id: &str) -> Result<Value, Failure> {
+        let order = self
+            .orders
+            .iter_mut()
+            .find(|order| order.id == id)
+            .ok_or(ErrorCode::OrderNotFound)?;
+        if matches!(order.status, OrderStatus::Inactive | OrderStatus::Activated) {
+            order.status = OrderStatus::Canceled;
+        }
+        Ok(ok(Mode::Paper))
+    }
+
+    /// Book an executed order on the account and the positions
+    fn execute(&mut self, index: usize) {
+        let order = &self.orders[index];
+        let Some(isin) = order.isin.clone() else {
+            return;
+        };
+        let quantity = order.quantity.unwrap_or_default();
+        let total = order.estimated_price.unwrap_or_default();
+        let price = Price::from_raw(total.raw() / quantity.max(1));
+        let title = self
+            .instruments
+            .iter()
+            .find(|instrument| instrument.isin.as_ref() == Some(&isin))
+            .and_then(|instrument| instrument.title.clone())
+            .unwrap_or_default();
+        let sell = matches!(order.side, Some(OrderType::Sell));
+        let statement = Statement {
+            id: Some(self.next_id("sta_")),
+            order_id: Some(self.orders[index].id.clone()),
+            external_id: None,
+            statement_type: if sell {
+                StatementType::OrderSell
+            } else {
+                StatementType::OrderBuy
+            },
+            quantity,
+            isin: isin.clone(),
+            isin_title: title.clone(),
+            date: Utc::now().date_naive(),
+            created_at: Utc::now(),
+        };
+        self.statements.push(statement);
+        let (statement_type, amount) = match sell {
+            true => (BankStatementType::OrderSell, total),
+            false => (BankStatementType::OrderBuy, -total),
+        };
+        self.book(statement_type, amount, Some((&isin, &title, quantity)));
+
+        let account = &mut self.account;
+        if sell {
+            account.balance += total;
+            account.cash_to_invest += total;
+            account.amount_sold_intraday += total;
+            if let Some(position) = self.positions.iter_mut().find(|p| p.isin == isin) {
+                position.quantity -= quantity;
+                position.estimated_price_total =
+                    price.checked_total(position.quantity).unwrap_or_default();
+            }
+            self.positions.retain(|position| position.quantity > 0);
+            return;
+        }
+        account.balance -= total;
+        account.cash_to_invest -= total;
+        account.cash_to_withdraw -= total;
+        account.amount_bought_intraday += total;
+        match self.positions.iter_mut().find(|p| p.isin == isin) {
+            Some(position) => {
+                let bought = position
+                    .buy_price_avg
+                    .checked_total(position.quantity)
+                    .unwrap_or_default()
+                    + total;
+                position.quantity += quantity;
+                position.buy_price_avg = Price::from_raw(bought.raw() / position.quantity);
+                position.estimated_price = price;
+                position.estimated_price_total =
+                    price.checked_total(position.quantity).unwrap_or_default();
+            }
+            None => self.positions.push(Position {
+                isin,
+                isin_title: title,
+                quantity,
+                buy_price_avg: price,
+                estimated_price_total: total,
+                estimated_price: price,
+            }),
+        }
+    }
+
+    /// The amount of shares held of an instrument
+    fn holdings(&self, isin: &Isin) -> i64 {
+        self.positions
+            .iter()
+            .filter(|position| position.isin == *isin)
+            .map(|position| position.quantity)
+            .sum()
+    }
+
+    /// Change the settings of the account that are set in the update
+    fn update_account(&mut self, update: AccountUpdateBody) -> Result<Value, Failure> {
+        let account = &mut self.account;
+        let fields = [
+            (&mut account.billing_address, update.billing_address),
+            (&mut account.billing_email, update.billing_email),
+            (&mut account.billing_name, update.billing_name),
+            (&mut account.billing_vat, update.billing_vat),
+            (&mut account.address_country, update.address_country),
+        ];
+        for (field, value) in fields {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+        Ok(ok(Mode::Paper))
+    }
+
+    /// The withdrawal with the given id
+    fn withdrawal(&self, id: &str) -> Result<&Withdrawal, Failure> {
+        self.withdrawals
+            .iter()
+            .find(|withdrawal| withdrawal.id == id)
+            .ok_or_else(|| ErrorCode::WithdrawalNotFound.into())
+    }
+
+    /// Submit a withdrawal, if the PIN is right and the account has enough cash.
+    /// A withdrawal with the idempotency key of an earlier one is not submitted again
+    fn post_withdrawal(&mut self, request: WithdrawalBody) -> Result<Value, Failure> {
+        match request.pin {
+            None => return Err(ErrorCode::PinMissing.into()),
+            Some(pin) if pin != self.pin => return Err(ErrorCode::PinInvalid.into()),
+            Some(_) => {}
+        }
+        if request.idempotency.is_some()
+            && self
+                .withdrawals
+                .iter()
+                .any(|withdrawal| withdrawal.idempotency == request.idempotency)
+        {
+            return Ok(ok(Mode::Paper));
+        }
+        if request.amount > self.account.cash_to_withdraw {
+            return Err(ErrorCode::WithdrawInsufficientFunds.into());
+        }
+        self.account.balance -= request.amount;
+        self.account.cash_to_invest -= request.amount;
+        self.account.cash_to_withdraw -= request.amount;
+        self.account.amount_open_withdrawals += request.amount;
+        self.book(BankStatementType::PayOut, -request.amount, None);
+        let withdrawal = Withdrawal {
+            id: self.next_id("wtd_"),
+            amount: request.amount,
+            created_at: Utc::now(),
+            date: None,
+            idempotency: request.idempotency,
+        };
+        self.withdrawals.push(withdrawal);
+        Ok(ok(Mode::Paper))
+    }
+}
+
+/// Body of an account update, as the mock reads it
+#[derive(serde::Deserialize)]
+struct AccountUpdateBody {
+    /// New billing address
+    billing_address: Option<String>,
+    /// New billing email address
+    billing_email: Option<String>,
+    /// New billing name
+    billing_name: Option<String>,
+    /// New billing VAT number
+    billing_vat: Option<String>,
+    /// New country of the address
+    address_country: Option<String>,
+}
+
+/// Body of a withdrawal request, as the mock reads it
+#[derive(serde::Deserialize)]
+struct WithdrawalBody {
+    /// Amount to withdraw
+    amount: Amount,
+    /// PIN of the account
+    pin: Option<i64>,
+    /// Idempotency key of the withdrawal
+    idempotency: Option<String>,
+}
+
+/// Whether `time` lies within the `from` and `to` query parameters. Without a time, only
+/// queries without these parameters match
+fn in_range(query: &HashMap<String, String>, time: Option<DateTime<Utc>>) -> bool {
+    let bound = |key: &str| {
+        query
+            .get(key)
+            .map(|bound| DateTime::parse_from_rfc3339(bound).ok())
+    };
+    match (bound("from"), bound("to"), time) {
+        (None, None, _) => true,
+        (from, to, Some(time)) => {
+            from.is_none_or(|from| from.is_some_and(|from| time >= from))
+                && to.is_none_or(|to| to.is_some_and(|to| time <= to))
+        }
+        (_, _, None) => false,
+    }
+}
+
+/// Reverse the items, which are in the order they were created, if the query asks for the
+/// newest first
+fn sort(query: &HashMap<String, String>, items: &mut [Value]) {
+    if query
+        .get("sorting")
+        .is_some_and(|sorting| sorting == "desc")
+    {
+        items.reverse();
+    }
+}
+
+/// Lock the state, even if a request panicked while holding the lock
+fn lock(state: &Mutex<MockState>) -> MutexGuard<'_, MockState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Answer a single request
+fn serve(state: &Mutex<MockState>, base_url: &str, mut request: tiny_http::Request) {
+    let mut body = String::new();
+    let read = request.as_reader().read_to_string(&mut body);
+    let url = Url::parse(&format!("http://localhost{}", request.url()));
+    let method = Method::from_bytes(request.method().as_str().as_bytes());
+    let authorized = request.headers().iter().any(|header| {
+        header.field.equiv("Authorization")
+            && header
+                .value
+                .as_str()
+                .strip_prefix("Bearer ")
+                .is_some_and(|key| !key.trim().is_empty())
+    });
+    let range = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Range"))
+        .map(|header| header.value.to_string());
+
+    let (path, mode, result) = match (read, url, method) {
+        (Ok(_), Ok(url), Ok(method)) => {
+            let path = url.path().trim_matches('/');
+            let path = path.strip_prefix("v1").unwrap_or(path).trim_matches('/');
+            let mode = match path.split('/').next() {
+                Some("instruments" | "venues" | "quotes") => Mode::MarketData,
+                _ => Mode::Paper,
+            };
+            let query = url.query_pairs().into_owned().collect();
+            let document = path
+                .strip_prefix("account/documents/")
+                .filter(|id| method == Method::GET && !id.contains('/'));
+            let result = match (authorized, document) {
+                (false, _) => Err(ErrorCode::Unauthorized.into()),
+                (true, Some(id)) => lock(state).download(path, id, range.as_deref()),
+                (true, None) => lock(state)
+                    .handle(base_url, &method, path, &query, &body)
+                    .map(Reply::json),
+            };
+            (path.to_string(), mode, result)
+        }
+        _ => (String::new(), Mode::Paper, Err(Failure::validation_error())),
+    };
+
+    let reply = result.unwrap_or_else(|failure| {
+        let message = format!("{} ({})", failure.code.as_str().replace('_', " "), path);
+        let body = json!({
+            "time": Utc::now(),
+            "mode": mode,
+            "status": "error",
+            "error_code": failure.code,
+            "error_message": message,
+        });
+        Reply {
+            status: failure.status,
+            ..Reply::json(body)
+        }
+    });
+    let mut response = tiny_http::Response::from_data(reply.body)
+        .with_status_code(reply.status.as_u16())
+        .with_header(header("Content-Type", reply.content_type));
+    if let Some(content_range) = reply.content_range {
+        response = response.with_header(header("Content-Range", &content_range));
+    }
+    if reply.status == StatusCode::TOO_MANY_REQUESTS {
+        response = response.with_header(header("Retry-After", "1"));
+    }
+    // The client hung up, there is nobody left to tell
+    let _ = request.respond(response);
+}
+
+/// A response header
+fn header(name: &str, value: &str) -> tiny_http::Header {
+    tiny_http::Header::from_bytes(name, value).expect("static headers are valid")
+}
+
+/// Deserialize a json request body
+fn parse<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, Failure> {
+    serde_json::from_str(body).map_err(|_| Failure::validation_error())
+}
+
+/// Serialize the items of a list endpoint
+fn values<'a, T: Serialize + 'a>(items: impl IntoIterator<Item = &'a T>) -> Vec<Value> {
+    items
+        .into_iter()
+        .map(|item| serde_json::to_value(item).expect("mock state serializes"))
+        .collect()
+}
+
+/// A successful response without results
+fn ok(mode: Mode) -> Value {
+    json!({ "time": Utc::now(), "mode": mode, "status": "ok" })
+}
+
+/// A successful response with a single result
+fn results<T: Serialize>(mode: Mode, results: &T) -> Value {
+    json!({ "time": Utc::now(), "mode": mode, "status": "ok", "results": results })
+}
+
+/// A page of a list endpoint, selected by the `limit` and `page` query parameters
+fn paginate(
+    base_url: &str,
+    path: &str,
+    query: &HashMap<String, String>,
+    items: Vec<Value>,
+) -> Result<Value, Failure> {
+    let number = |key: &str, default: usize| match query.get(key) {
+        Some(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(Failure::validation_error),
+        None => Ok(default),
+    };
+    let limit = number("limit", DEFAULT_LIMIT)?;
+    let page = number("page", 1)?;
+    let total = items.len();
+    let pages = total.div_ceil(limit).max(1);
+    // Links keep the filters of the request
+    let filters: BTreeMap<&str, &str> = query
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "limit" | "page"))
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    let link = |page: usize| {
+        let mut params = filters.clone();
+        let (limit, page) = (limit.to_string(), page.to_string());
+        params.insert("limit", &limit);
+        params.insert("page", &page);
+        let params = serde_urlencoded::to_string(&params).unwrap_or_default();
+        format!("{}/{}?{}", base_url, path, params)
+    };
+    let mode = if path.starts_with("instruments") || path.starts_with("venues") {
+        Mode::MarketData
+    } else {
+        Mode::Paper
+    };
+    let results: Vec<Value> = items
+        .into_iter()
+        .skip((page - 1) * limit)
+        .take(limit)
+        .collect();
+    Ok(json!({
+        "time": Utc::now(),
+        "status": "ok",
+        "mode": mode,
+        "results": results,
+        "previous": (page > 1).then(|| link(page - 1)),
+        "next": (page < pages).then(|| link(page + 1)),
+        "total": total,
+        "page": page,
+        "pages": pages,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Method;
+
+    use super::MockServer;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_injected_errors_are_returned_once() {
+        let server = MockServer::start().unwrap();
+        let client = server.trading_client();
+        server.fail_next(Method::GET, "orders/ord_missing", ErrorCode::OrderNotFound);
+        server.fail_next(Method::GET, "account", ErrorCode::TradingBlocked);
+
+        let error = client.get_order("ord_missing").unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::OrderNotFound));
+        assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+
+        let error = client.get_account_information().unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::TradingBlocked));
+        assert!(client.get_account_information().is_ok());
+    }
+
+    #[test]
+    fn test_requests_without_api_key_are_rejected() {
+        let server = MockServer::start().unwrap();
+        let response = reqwest::blocking::get(format!("{}/account", server.url())).unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_list_endpoints_paginate() {
+        let server = MockServer::start().unwrap();
+        let client = server.data_client();
+        let first = client.get_venues().unwrap();
+        assert_eq!(first.total, 1);
+
+        let url = format!("{}/instruments?limit=1", server.url());
+        let page = reqwest::blocking::Client::new()
+            .get(url)
+            .bearer_auth("key")
+            .send()
+            .unwrap()
+            .json::<crate::api::PaginationResponse<super::InstrumentInfo>>()
+            .unwrap();
+        assert_eq!(page.pages, 2);
+        let items = client.paginate(page).collect_all(10).unwrap();
+        assert_eq!(items.len(), 2);
+    }
+}
@@ -0,0 +1,365 @@
+//! Amounts of money in the fixed-point format of the API
+//!
+//! lemon.markets sends every amount and price as an integer in hundredths of a cent, so
+//! `12.34€` is `123400` on the wire (see the [numbers page](https://docs.lemon.markets/numbers)).
+//! [`Amount`] and [`Price`] keep that representation, so converting to and from decimal euros
+//! is lossless.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Wire units per euro
+const UNITS_PER_EURO: i64 = 10_000;
+/// Wire units per cent
+const UNITS_PER_CENT: i64 = 100;
+/// Decimal places of the wire format
+const DECIMALS: usize = 4;
+/// Panic message of the operators on overflow
+const OVERFLOW: &str = "amount of money overflowed";
+
+/// Error returned when a string is not an amount of euros
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid amount of euros {input:?}, expected e.g. \"12.34\" with at most 4 decimal places")]
+pub struct ParseMoneyError {
+    /// The string that failed to parse
+    input: String,
+}
+
+impl ParseMoneyError {
+    /// Create an error for the given input
+    fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+        }
+    }
+}
+
+/// Parse decimal euros, e.g. `-12.34` or `€0.0001`, into wire units
+fn parse_units(input: &str) -> Result<i64, ParseMoneyError> {
+    let error = || ParseMoneyError::new(input);
+    let s = input.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let s = s
+        .strip_prefix('€')
+        .or_else(|| s.strip_suffix('€'))
+        .unwrap_or(s);
+    let s = s.trim();
+    let (euros, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if euros.is_empty() || !all_digits(euros) || !all_digits(fraction) || fraction.len() > DECIMALS
+    {
+        return Err(error());
+    }
+    let euros: i64 = euros.parse().map_err(|_| error())?;
+    let fraction: i64 = format!("{:0<width$}", fraction, width = DECIMALS)
+        .parse()
+        .map_err(|_| error())?;
+    let units = euros
+        .checked_mul(UNITS_PER_EURO)
+        .and_then(|units| units.checked_add(fraction))
+        .ok_or_else(error)?;
+    Ok(if negative { -units } else { units })
+}
+
+/// Write wire units as euros with a currency symbol.
+///
+/// Without a precision, all significant decimal places are written, and at least two. With a
+/// precision, the value is rounded half away from zero.
+fn fmt_units(units: i64, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let negative = units < 0;
+    let units = units.unsigned_abs();
+    let (euros, fraction) = match f.precision() {
+        Some(precision) if precision < DECIMALS => {
+            let scale = 10u64.pow((DECIMALS - precision) as u32);
+            let rounded = (units + scale / 2) / scale;
+            let per_euro = 10u64.pow(precision as u32);
+            let fraction = match precision {
+                0 => String::new(),
+                _ => format!("{:0width$}", rounded % per_euro, width = precision),
+            };
+            (rounded / per_euro, fraction)
+        }
+        _ => {
+            let per_euro = UNITS_PER_EURO as u64;
+            let fraction = format!("{:04}", units % per_euro);
+            let significant = fraction.trim_end_matches('0').len().max(2);
+            (units / per_euro, fraction[..significant].to_string())
+        }
+    };
+    // Values that round to zero are written without a sign
+    let is_zero = euros == 0 && fraction.bytes().all(|b| b == b'0');
+    let sign = if negative && !is_zero { "-" } else { "" };
+    if fraction.is_empty() {
+        write!(f, "{}€{}", sign, euros)
+    } else {
+        write!(f, "{}€{}.{}", sign, euros, fraction)
+    }
+}
+
+/// Defines a fixed-point money type in wire units
+macro_rules! money {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        ///
+        /// # Panics
+        ///
+        /// `+`, `-`, `+=`, `-=`, negation and [`Sum`] panic on overflow, in release builds
+        /// too. Use [`checked_add`](Self::checked_add), [`checked_sub`](Self::checked_sub) and
+        /// [`checked_neg`](Self::checked_neg) to handle it instead.
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(i64);
+
+        impl $name {
+            /// Zero euros
+            pub const ZERO: Self = Self(0);
+
+            /// Create from the wire format, in hundredths of a cent
+            pub const fn from_raw(raw: i64) -> Self {
+                Self(raw)
+            }
+
+            /// The value in the wire format, in hundredths of a cent
+            pub const fn raw(self) -> i64 {
+                self.0
+            }
+
+            /// Create from whole euros. `None` on overflow
+            pub fn from_euros(euros: i64) -> Option<Self> {
+                euros.checked_mul(UNITS_PER_EURO).map(Self)
+            }
+
+            /// Create from whole cents. `None` on overflow
+            pub fn from_cents(cents: i64) -> Option<Self> {
+                cents.checked_mul(UNITS_PER_CENT).map(Self)
+            }
+
+            /// The value in euros as a float, e.g. for charts. Use [`Display`](fmt::Display)
+            /// or the wire format for exact values.
+            pub fn to_euros_f64(self) -> f64 {
+                self.0 as f64 / UNITS_PER_EURO as f64
+            }
+
+            /// Whether the value is below zero
+            pub const fn is_negative(self) -> bool {
+                self.0 < 0
+            }
+
+            /// Add two values. `None` on overflow
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.0.checked_add(rhs.0).map(Self)
+            }
+
+            /// Subtract two values. `None` on overflow
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                self.0.checked_sub(rhs.0).map(Self)
+            }
+
+            /// Negate the value. `None` on overflow
+            pub fn checked_neg(self) -> Option<Self> {
+                self.0.checked_neg().map(Self)
+            }
+
+            /// Multiply by an integer factor. `None` on overflow
+            pub fn checked_mul(self, factor: i64) -> Option<Self> {
+                self.0.checked_mul(factor).map(Self)
+            }
+
+            /// Divide by an integer, rounding towards zero. `None` if `divisor` is zero
+            pub fn checked_div(self, divisor: i64) -> Option<Self> {
+                self.0.checked_div(divisor).map(Self)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_units(self.0, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseMoneyError;
+
+            /// Parse decimal euros, e.g. `12.34`, `-0.5` or `€100`
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_units(s).map(Self)
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                self.checked_add(rhs).expect(OVERFLOW)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                self.checked_sub(rhs).expect(OVERFLOW)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                self.checked_neg().expect(OVERFLOW)
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::ZERO, Add::add)
+            }
+        }
+
+        #[cfg(feature = "decimal")]
+        impl From<$name> for rust_decimal::Decimal {
+            fn from(value: $name) -> Self {
+                rust_decimal::Decimal::new(value.0, DECIMALS as u32)
+            }
+        }
+
+        #[cfg(feature = "decimal")]
+        impl TryFrom<rust_decimal::Decimal> for $name {
+            type Error = ParseMoneyError;
+
+            /// Convert decimal euros. Fails if the value has more than 4 significant decimal
+            /// places or does not fit.
+            fn try_from(value: rust_decimal::Decimal) -> Result<Self, Self::Error> {
+                // Trailing zeros, e.g. of `1.50000`, do not count as decimal places
+                value.normalize().to_string().parse()
+            }
+        }
+    };
+}
+
+money! {
+    /// An amount of money, e.g. a balance, the total of an order or a fee
+    ///
+    /// Serializes to the wire format, an integer in hundredths of a cent.
+    ///
+    /// ```
+    /// use septoria::money::Amount;
+    ///
+    /// let amount: Amount = "12.34".parse().unwrap();
+    /// assert_eq!(amount.raw(), 123_400);
+    /// assert_eq!(amount.to_string(), "€12.34");
+    /// ```
+    Amount
+}
+
+money! {
+    /// The price of a single share
+    ///
+    /// Serializes to the wire format, an integer in hundredths of a cent.
+    Price
+}
+
+impl Price {
+    /// The amount `quantity` shares cost at this price. `None` on overflow
+    pub fn checked_total(self, quantity: i64) -> Option<Amount> {
+        self.0.checked_mul(quantity).map(Amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Amount, ParseMoneyError, Price};
+
+    #[test]
+    fn test_parse_and_display_round_trip() {
+        for (input, raw, display) in [
+            ("12.34", 123_400, "€12.34"),
+            ("€0.0001", 1, "€0.0001"),
+            ("-5", -50_000, "-€5.00"),
+            ("100.5€", 1_005_000, "€100.50"),
+        ] {
+            let amount: Amount = input.parse().unwrap();
+            assert_eq!(amount.raw(), raw);
+            assert_eq!(amount.to_string(), display);
+            assert_eq!(display.parse::<Amount>().unwrap(), amount);
+        }
+        assert_eq!(format!("{:.2}", Amount::from_raw(123_450)), "€12.35");
+        assert_eq!(format!("{:.0}", Amount::from_raw(-4_999)), "€0");
+        assert_eq!(format!("{:.0}", Amount::from_raw(-5_000)), "-€1");
+
+        for input in ["", "12.34567", "1,5", "1.-5", "abc", "99999999999999999"] {
+            assert_eq!(
+                input.parse::<Amount>(),
+                Err(ParseMoneyError::new(input)),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_serializes_to_the_wire_format() {
+        let price: Price = serde_json::from_str("1470000").unwrap();
+        assert_eq!(price, Price::from_euros(147).unwrap());
+        assert_eq!(serde_json::to_string(&price).unwrap(), "1470000");
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let price = Price::from_cents(1_050).unwrap();
+        assert_eq!(price.checked_total(3), Amount::from_cents(3_150));
+        assert_eq!(Price::from_raw(i64::MAX).checked_total(2), None);
+        let amount = Amount::from_euros(10).unwrap();
+        assert_eq!(
+            amount.checked_sub(Amount::from_euros(15).unwrap()),
+            Amount::from_euros(-5)
+        );
+        assert_eq!(amount.checked_div(0), None);
+        assert_eq!(Amount::from_raw(i64::MAX).checked_add(amount), None);
+        assert_eq!(Amount::from_raw(i64::MIN).checked_neg(), None);
+        assert_eq!(-amount, Amount::from_euros(-10).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "amount of money overflowed")]
+    fn test_operators_panic_on_overflow() {
+        let _ = Amount::from_raw(i64::MAX) + Amount::from_raw(1);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_conversions() {
+        use rust_decimal::Decimal;
+
+        let amount = Amount::from_raw(-123_456);
+        let decimal = Decimal::from(amount);
+        assert_eq!(decimal.to_string(), "-12.3456");
+        assert_eq!(Amount::try_from(decimal), Ok(amount));
+        assert!(Amount::try_from(Decimal::new(1, 5)).is_err());
+        assert_eq!(
+            Price::try_from(Decimal::new(150_000, 5)),
+            Ok(Price::from_cents(150).unwrap())
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::vec::IntoIter;
+
+use crate::api::PaginationResponse;
+use crate::error::Error;
+
+/// Fetches the page behind a `next` url
+type FetchPage<'a, T> = Box<dyn FnMut(&str) -> Result<PaginationResponse<T>, Error> + 'a>;
+
+/// Iterator over the items of a paginated endpoint
+///
+/// Starts with the items of the first page, and fetches the page behind `next` whenever the
+/// current page runs out. Get one from `paginate` on any client, e.g.
+/// [`TradingClient::paginate`](crate::client::TradingClient::paginate).
+pub struct Paginator<'a, T> {
+    /// Fetches the page behind a `next` url
+    fetch: FetchPage<'a, T>,
+    /// Items and position of the current page
+    state: PageState<T>,
+}
+
+/// The items left on the current page, and where to find the next one
+pub(crate) struct PageState<T> {
+    /// Items of the current page that have not been yielded yet
+    items: IntoIter<T>,
+    /// Url of the next page
+    next: Option<String>,
+    /// The number of pages fetched so far, including the first one
+    pages: usize,
+}
+
+impl<T> PageState<T> {
+    /// Start with the first page
+    pub(crate) fn new(first_page: PaginationResponse<T>) -> Self {
+        let mut state = Self {
+            items: vec![].into_iter(),
+            next: None,
+            pages: 0,
+        };
+        state.push_page(first_page);
+        state
+    }
+
+    /// Replace the current page with the one that was just fetched
+    pub(crate) fn push_page(&mut self, page: PaginationResponse<T>) {
+        self.items = page.results.unwrap_or_default().into_iter();
+        self.next = page.next.filter(|next| !next.is_empty());
+        self.pages += 1;
+    }
+
+    /// The next item of the current page
+    pub(crate) fn next_item(&mut self) -> Option<T> {
+        self.items.next()
+    }
+
+    /// Take all items left on the current page
+    pub(crate) fn drain_items(&mut self) -> IntoIter<T> {
+        std::mem::take(&mut self.items)
+    }
+
+    /// Take the url of the next page, unless `max_pages` pages have been fetched already
+    pub(crate) fn take_next(&mut self, max_pages: usize) -> Option<String> {
+        if self.pages >= max_pages {
+            return None;
+        }
+        self.next.take()
+    }
+}
+
+impl<'a, T> Paginator<'a, T> {
+    /// Create a paginator that fetches further pages with `fetch`
+    pub(crate) fn new(
+        first_page: PaginationResponse<T>,
+        fetch: impl FnMut(&str) -> Result<PaginationResponse<T>, Error> + 'a,
+    ) -> Self {
+        Self {
+            fetch: Box::new(fetch),
+            state: PageState::new(first_page),
+        }
+    }
+
+    /// Collect the items of up to `max_pages` pages, including the pages already fetched
+    pub fn collect_all(mut self, max_pages: usize) -> Result<Vec<T>, Error> {
+        let mut items = vec![];
+        loop {
+            items.extend(self.state.drain_items());
+            let Some(next) = self.state.take_next(max_pages) else {
+                return Ok(items);
+            };
+            let page = (self.fetch)(&next)?;
+            self.state.push_page(page);
+        }
+    }
+}
+
+impl<T> Iterator for Paginator<'_, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.state.next_item() {
+                return Some(Ok(item));
+            }
+            let next = self.state.take_next(usize::MAX)?;
+            match (self.fetch)(&next) {
+                Ok(page) => self.state.push_page(page),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Stream over the items of `first_page` and all following pages, fetched with `fetch`
+#[cfg(feature = "async")]
+pub(crate) fn stream<'a, T, F, Fut>(
+    first_page: PaginationResponse<T>,
+    fetch: F,
+) -> impl futures_util::Stream<Item = Result<T, Error>> + 'a
+where
+    T: 'a,
+    F: Fn(String) -> Fut + 'a,
+    Fut: std::future::Future<Output = Result<PaginationResponse<T>, Error>> + 'a,
+{
+    let state = PageState::new(first_page);
+    futures_util::stream::unfold(Some((state, fetch)), |pages| async move {
+        let (mut state, fetch) = pages?;
+        loop {
+            if let Some(item) = state.next_item() {
+                return Some((Ok(item), Some((state, fetch))));
+            }
+            let next = state.take_next(usize::MAX)?;
+            match fetch(next).await {
+                Ok(page) => state.push_page(page),
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    })
+}
+
+/// Collect the items of `first_page` and of following pages, up to `max_pages` pages
+#[cfg(feature = "async")]
+pub(crate) async fn collect_all<T, F, Fut>(
+    first_page: PaginationResponse<T>,
+    max_pages: usize,
+    fetch: F,
+) -> Result<Vec<T>, Error>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<PaginationResponse<T>, Error>>,
+{
+    let mut state = PageState::new(first_page);
+    let mut items = vec![];
+    loop {
+        items.extend(state.drain_items());
+        let Some(next) = state.take_next(max_pages) else {
+            return Ok(items);
+        };
+        state.push_page(fetch(next).await?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Paginator;
+    use crate::api::PaginationResponse;
+
+    fn page(page: i64, results: Vec<i64>) -> PaginationResponse<i64> {
+        let next = (page < 3).then(|| format!("https://example.com/v1/items/?page={}", page + 1));
+        serde_json::from_value(json!({
+            "time": "2022-02-14T20:44:03.759+00:00",
+            "status": "ok",
+            "results": results,
+            "next": next,
+            "total": 5,
+            "page": page,
+            "pages": 3,
+        }))
+        .unwrap()
+    }
+
+    fn fetch(url: &str) -> Result<PaginationResponse<i64>, crate::error::Error> {
+        match url.rsplit('=').next() {
+            Some("2") => Ok(page(2, vec![3, 4])),
+            Some("3") => Ok(page(3, vec![5])),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_paginator_follows_next() {
+        let items = Paginator::new(page(1, vec![1, 2]), fetch)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(items, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_collect_all_stops_after_max_pages() {
+        let items = Paginator::new(page(1, vec![1, 2]), fetch)
+            .collect_all(2)
+            .unwrap();
+        assert_eq!(items, vec![1, 2, 3, 4]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_stream_follows_next() {
+        use futures_util::StreamExt;
+
+        let stream = super::stream(page(1, vec![1, 2]), |next| async move { fetch(&next) });
+        let items: Vec<i64> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(items, vec![1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::trading::account::Plan;
+
+/// Token bucket rate limiter that can be shared between clients and threads
+///
+/// Every request takes one token from the bucket, and the bucket refills at a constant rate.
+/// When the bucket is empty, the request waits until its token has been refilled instead of
+/// being rejected by the API. Cloning the limiter shares the bucket, so one limiter can be
+/// passed to several [`TradingClient`](crate::client::TradingClient)s and
+/// [`DataClient`](crate::data_client::DataClient)s that use the same API key.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// The bucket, shared between all clones
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+/// State of the token bucket
+#[derive(Debug)]
+struct Bucket {
+    /// The maximum amount of tokens, i.e. the largest burst of requests
+    capacity: f64,
+    /// Tokens currently available. Negative when requests are waiting for a token
+    tokens: f64,
+    /// Tokens added per second
+    refill_rate: f64,
+    /// When the tokens were last refilled
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `requests` requests per `period`, in bursts of up to `requests` requests
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` or `period` is zero.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        assert!(
+            requests > 0,
+            "a rate limiter needs to allow at least one request"
+        );
+        assert!(!period.is_zero(), "a rate limiter needs a non-zero period");
+        let capacity = f64::from(requests);
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity,
+                tokens: capacity,
+                refill_rate: capacity / period.as_secs_f64(),
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Allow `requests` requests per second
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allow `requests` requests per minute
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// A limiter for the given trading plan, as in `AccountResults::trading_plan`
+    ///
+    /// Plans this crate does not know get the limit of [`Plan::Go`]. Use
+    /// [`RateLimiter::new`] if your key has a custom limit.
+    pub fn for_trading_plan(plan: &Plan) -> Self {
+        match plan {
+            Plan::Go => Self::per_minute(10),
+            Plan::Investor => Self::per_minute(100),
+            Plan::Trader => Self::per_minute(1_000),
+            Plan::B2b => Self::per_minute(10_000),
+            Plan::Other(_) => Self::for_trading_plan(&Plan::Go),
+        }
+    }
+
+    /// A limiter for the given market data plan, as in `AccountResults::data_plan`
+    ///
+    /// Plans this crate does not know get the limit of [`Plan::Go`]. Use
+    /// [`RateLimiter::new`] if your key has a custom limit.
+    pub fn for_data_plan(plan: &Plan) -> Self {
+        match plan {
+            Plan::Go => Self::per_minute(10),
+            Plan::Investor => Self::per_minute(100),
+            Plan::Trader => Self::per_minute(1_000),
+            Plan::B2b => Self::per_minute(10_000),
+            Plan::Other(_) => Self::for_data_plan(&Plan::Go),
+        }
+    }
+
+    /// Block the current thread until a request is allowed
+    pub fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Wait until a request is allowed
+    #[cfg(feature = "async")]
+    pub async fn acquire_async(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take a token, and return how long to wait until it is refilled
+    fn reserve(&self, now: Instant) -> Duration {
+        // The bucket is consistent after every update, so a poisoned lock is still usable
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * bucket.refill_rate).min(bucket.capacity);
+        bucket.last_refill = bucket.last_refill.max(now);
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / bucket.refill_rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+    use crate::api::trading::account::Plan;
+
+    #[test]
+    fn test_bucket_allows_bursts_then_queues() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(1));
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_clones_share_the_bucket() {
+        let limiter = RateLimiter::per_second(1);
+        let clone = limiter.clone();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(clone.reserve(now), Duration::from_secs(1));
+        assert_eq!(clone.reserve(now + Duration::from_secs(3)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_plan_limits() {
+        let now = Instant::now();
+        for (plan, requests) in [
+            (Plan::Go, 10),
+            (Plan::Investor, 100),
+            (Plan::Trader, 1_000),
+            (Plan::B2b, 10_000),
+            (Plan::Other("enterprise".to_string()), 10),
+        ] {
+            for limiter in [
+                RateLimiter::for_trading_plan(&plan),
+                RateLimiter::for_data_plan(&plan),
+            ] {
+                for _ in 0..requests {
+                    assert_eq!(limiter.reserve(now), Duration::ZERO, "{plan:?}");
+                }
+                assert!(!limiter.reserve(now).is_zero(), "{plan:?}");
+            }
+        }
+    }
+}
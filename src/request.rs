@@ -0,0 +1,454 @@
+//! Request core shared by the blocking and async clients
+//!
+//! Endpoints describe the call they want to make as a [`Request`]. The clients turn it into an
+//! [`HttpRequest`] and hand it to their transport, so URL building, authentication, retries and
+//! response handling live in one place.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::Read;
+use std::marker::PhantomData;
+
+#[cfg(feature = "async")]
+use futures_util::StreamExt;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{Error, LemonError};
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+#[cfg(feature = "async")]
+use crate::transport::{AsyncTransport, BodyStream};
+use crate::transport::{BodyReader, HttpRequest, HttpResponse, Transport};
+
+/// A single API call, independent of the client that executes it.
+#[derive(Debug)]
+pub(crate) struct Request<T> {
+    /// HTTP method of the call
+    pub(crate) method: Method,
+    /// Path relative to the base url of the client
+    pub(crate) path: String,
+    /// Query parameters as name and value, e.g. `("limit", "10")`. Encoded when the url is built
+    pub(crate) query: Vec<(String, String)>,
+    /// Json encoded body
+    pub(crate) body: Option<String>,
+    /// Whether sending the request twice has the same effect as sending it once
+    pub(crate) idempotent: bool,
+    /// Extra headers, e.g. `Range`
+    pub(crate) headers: HeaderMap,
+    /// The type the response body deserializes into
+    response: PhantomData<fn() -> T>,
+}
+
+/// The `Authorization` header value for the given API key
+pub(crate) fn bearer_auth(api_key: &str) -> Result<HeaderValue, Error> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", api_key))
+        .map_err(|_| Error::Config("API key is not a valid header value".to_string()))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// The query parameters `query` serializes to, as name and value
+fn query_pairs<Q: Serialize>(query: &Q) -> Result<Vec<(String, String)>, Error> {
+    let params: Vec<(String, Value)> = match serde_json::to_value(query)? {
+        Value::Null => vec![],
+        Value::Object(params) => params.into_iter().collect(),
+        Value::Array(params) => params
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?,
+        query => return Err(Error::Str(format!("invalid query {}", query))),
+    };
+    let mut pairs = vec![];
+    for (name, value) in params {
+        let value = match value {
+            Value::Null => continue,
+            Value::Array(items) if items.is_empty() => continue,
+            Value::Array(items) => items
+                .into_iter()
+                .map(query_value)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+            value => query_value(value)?,
+        };
+        pairs.push((name, value));
+    }
+    Ok(pairs)
+}
+
+/// A single query value as text. Strings are taken as they are, without quotes
+fn query_value(value: Value) -> Result<String, Error> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+        value => Err(Error::Str(format!("invalid query value {}", value))),
+    }
+}
+
+/// The error for an unsuccessful response.
+/// Error bodies that are not a [`LemonError`] are kept as they are.
+fn error_response(status: StatusCode, body: &[u8]) -> Error {
+    match serde_json::from_slice::<LemonError>(body) {
+        Ok(error) => Error::api(status, error),
+        Err(_) => Error::Http(status, String::from_utf8_lossy(body).into_owned()),
+    }
+}
+
+impl<T: DeserializeOwned> Request<T> {
+    /// Create a request without query or body
+    fn new(method: Method, path: impl Into<String>) -> Self {
+        Self {
+            idempotent: matches!(method, Method::GET | Method::PUT | Method::DELETE),
+            method,
+            path: path.into(),
+            query: vec![],
+            body: None,
+            headers: HeaderMap::new(),
+            response: PhantomData,
+        }
+    }
+
+    /// Create a GET request
+    pub(crate) fn get(path: impl Into<String>) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    /// Create a POST request with a json body
+    pub(crate) fn post<B: Serialize>(path: impl Into<String>, body: &B) -> Result<Self, Error> {
+        let mut request = Self::new(Method::POST, path);
+        request.body = Some(serde_json::to_string(body)?);
+        Ok(request)
+    }
+
+    /// Create a PUT request with a json body
+    pub(crate) fn put<B: Serialize>(path: impl Into<String>, body: &B) -> Result<Self, Error> {
+        let mut request = Self::new(Method::PUT, path);
+        request.body = Some(serde_json::to_string(body)?);
+        Ok(request)
+    }
+
+    /// Create a DELETE request
+    pub(crate) fn delete(path: impl Into<String>) -> Self {
+        Self::new(Method::DELETE, path)
+    }
+
+    /// Create a GET request for a url returned by the API, e.g. the `next` page of a
+    /// [`PaginationResponse`](crate::api::PaginationResponse).
+    ///
+    /// Only the path and query are taken from `url`, so the request goes to the same host as
+    /// every other request of the client.
+    pub(crate) fn follow(url: &str, base_url: &Url) -> Result<Self, Error> {
+        let url = base_url
+            .join(url)
+            .map_err(|e| Error::Str(format!("invalid url {:?}: {}", url, e)))?;
+        let path = url
+            .path()
+            .strip_prefix(base_url.path().trim_end_matches('/'))
+            .unwrap_or(url.path());
+        let mut request = Self::get(path);
+        request.query = url.query_pairs().into_owned().collect();
+        Ok(request)
+    }
+
+    /// Attach query parameters to the request.
+    ///
+    /// `query` is anything that serializes to a map or a list of name and value pairs, usually
+    /// a query struct. Missing values are left out, and lists are joined with commas.
+    pub(crate) fn query<Q: Serialize>(mut self, query: &Q) -> Result<Self, Error> {
+        self.query.extend(query_pairs(query)?);
+        Ok(self)
+    }
+
+    /// Mark the request as safe to retry, e.g. because it carries an idempotency key
+    pub(crate) fn idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent |= idempotent;
+        self
+    }
+
+    /// Send an extra header with the request
+    pub(crate) fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// The full url of the request, relative to `base_url`
+    pub(crate) fn url(&self, base_url: &Url) -> Result<Url, Error> {
+        let url = format!(
+            "{}/{}",
+            base_url.as_str().trim_end_matches('/'),
+            self.path.trim_start_matches('/')
+        );
+        let mut url =
+            Url::parse(&url).map_err(|e| Error::Str(format!("invalid url {:?}: {}", url, e)))?;
+        if !self.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.query);
+        }
+        Ok(url)
+    }
+
+    /// Turn a raw response into the expected type, or into an error
+    pub(crate) fn parse(&self, status: StatusCode, body: &[u8]) -> Result<T, Error> {
+        if status.is_success() {
+            return Ok(serde_json::from_slice(body)?);
+        }
+        Err(error_response(status, body))
+    }
+
+    /// The HTTP request to send, authenticated with `api_key`
+    pub(crate) fn http_request(&self, base_url: &Url, api_key: &str) -> Result<HttpRequest, Error> {
+        let url = self.url(base_url)?;
+        let mut headers = self.headers.clone();
+        headers.insert(AUTHORIZATION, bearer_auth(api_key)?);
+        if self.body.is_some() {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        }
+        Ok(HttpRequest {
+            method: self.method.clone(),
+            url,
+            headers,
+            body: self.body.clone(),
+        })
+    }
+
+    /// Turn the outcome of the last attempt into the expected type, or into an error
+    fn finish(&self, result: Result<HttpResponse, Error>) -> Result<T, Error> {
+        let response = result?;
+        self.parse(response.status, &response.body)
+    }
+
+    /// Keep the body of a successful response unread, without deserializing it
+    fn finish_reader(
+        &self,
+        result: Result<HttpResponse<BodyReader>, Error>,
+    ) -> Result<HttpResponse<BodyReader>, Error> {
+        let mut response = result?;
+        if response.status.is_success() {
+            return Ok(response);
+        }
+        let mut body = vec![];
+        response.body.read_to_end(&mut body)?;
+        Err(error_response(response.status, &body))
+    }
+
+    /// Keep the body of a successful response unread, without deserializing it
+    #[cfg(feature = "async")]
+    async fn finish_stream(
+        &self,
+        result: Result<HttpResponse<BodyStream>, Error>,
+    ) -> Result<HttpResponse<BodyStream>, Error> {
+        let mut response = result?;
+        if response.status.is_success() {
+            return Ok(response);
+        }
+        let mut body = vec![];
+        while let Some(chunk) = response.body.next().await {
+            body.extend(chunk?);
+        }
+        Err(error_response(response.status, &body))
+    }
+
+    /// Send the request through a blocking transport, retrying according to `retry`.
+    /// Every attempt waits for the rate limiter first.
+    pub(crate) fn send_blocking<Tr: Transport>(
+        self,
+        transport: &Tr,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Result<T, Error> {
+        let result = self.attempt_blocking(base_url, api_key, retry, rate_limiter, |request| {
+            transport.send(request)
+        });
+        self.finish(result)
+    }
+
+    /// Like [`send_blocking`](Self::send_blocking), but returns the response of a successful
+    /// request with its body unread instead of deserializing it, e.g. for file downloads
+    pub(crate) fn send_blocking_streaming<Tr: Transport>(
+        self,
+        transport: &Tr,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Result<HttpResponse<BodyReader>, Error> {
+        let result = self.attempt_blocking(base_url, api_key, retry, rate_limiter, |request| {
+            transport.send_streaming(request)
+        });
+        self.finish_reader(result)
+    }
+
+    /// Send the request with `send` until it succeeds or `retry` gives up, and return the
+    /// last outcome
+    fn attempt_blocking<B>(
+        &self,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+        send: impl Fn(HttpRequest) -> Result<HttpResponse<B>, Error>,
+    ) -> Result<HttpResponse<B>, Error> {
+        let request = self.http_request(base_url, api_key)?;
+        let mut attempt = 0;
+        loop {
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.acquire();
+            }
+            let result = send(request.clone());
+            match retry.delay(attempt, self.idempotent, &result) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return result,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Send the request through an async transport, retrying according to `retry`.
+    /// Every attempt waits for the rate limiter first.
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_async<Tr: AsyncTransport>(
+        self,
+        transport: &Tr,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Result<T, Error> {
+        let result = self
+            .attempt_async(base_url, api_key, retry, rate_limiter, |request| {
+                transport.send(request)
+            })
+            .await;
+        self.finish(result)
+    }
+
+    /// Like [`send_async`](Self::send_async), but returns the response of a successful request
+    /// with its body unread instead of deserializing it, e.g. for file downloads
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_async_streaming<Tr: AsyncTransport>(
+        self,
+        transport: &Tr,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+    ) -> Result<HttpResponse<BodyStream>, Error> {
+        let result = self
+            .attempt_async(base_url, api_key, retry, rate_limiter, |request| {
+                transport.send_streaming(request)
+            })
+            .await;
+        self.finish_stream(result).await
+    }
+
+    /// Send the request with `send` until it succeeds or `retry` gives up, and return the
+    /// last outcome
+    #[cfg(feature = "async")]
+    async fn attempt_async<B, F>(
+        &self,
+        base_url: &Url,
+        api_key: &str,
+        retry: &RetryPolicy,
+        rate_limiter: Option<&RateLimiter>,
+        send: impl Fn(HttpRequest) -> F,
+    ) -> Result<HttpResponse<B>, Error>
+    where
+        F: Future<Output = Result<HttpResponse<B>, Error>>,
+    {
+        let request = self.http_request(base_url, api_key)?;
+        let mut attempt = 0;
+        loop {
+            if let Some(rate_limiter) = rate_limiter {
+                rate_limiter.acquire_async().await;
+            }
+            let result = send(request.clone()).await;
+            match retry.delay(attempt, self.idempotent, &result) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return result,
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{StatusCode, Url};
+    use serde_json::json;
+
+    use super::Request;
+    use crate::error::{Error, ErrorCode};
+
+    #[test]
+    fn test_url_joins_base_path_and_query() {
+        let base_url = Url::parse("https://data.lemon.markets/v1/").unwrap();
+        let request = Request::<()>::get("/instruments/")
+            .query(&json!({ "search": "Aker & Co", "type": null }))
+            .unwrap();
+        assert_eq!(
+            request.url(&base_url).unwrap().as_str(),
+            "https://data.lemon.markets/v1/instruments/?search=Aker+%26+Co"
+        );
+    }
+
+    #[test]
+    fn test_query_pairs() {
+        let request = Request::<()>::get("positions/statements")
+            .query(&[
+                ("types", json!(["order_buy", "split"])),
+                ("limit", json!(10)),
+                ("isin", json!([])),
+            ])
+            .unwrap();
+        assert_eq!(
+            request.query,
+            [
+                ("types".to_string(), "order_buy,split".to_string()),
+                ("limit".to_string(), "10".to_string())
+            ]
+        );
+        assert!(Request::<()>::get("orders/").query(&"isin").is_err());
+    }
+
+    #[test]
+    fn test_follow_keeps_the_base_url() {
+        let base_url = Url::parse("http://localhost:8080/v1").unwrap();
+        let request = Request::<()>::follow(
+            "https://paper-trading.lemon.markets/v1/positions/statements?limit=10&page=2",
+            &base_url,
+        )
+        .unwrap();
+        assert_eq!(
+            request.url(&base_url).unwrap().as_str(),
+            "http://localhost:8080/v1/positions/statements?limit=10&page=2"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_responses() {
+        let request = Request::<()>::get("orders/");
+        let body = br#"{
+            "time": "2022-02-14T20:44:03.759+00:00",
+            "mode": "paper",
+            "status": "error",
+            "error_code": "insufficient_holdings",
+            "error_message": "insufficient holdings"
+        }"#;
+        let error = request.parse(StatusCode::BAD_REQUEST, body).unwrap_err();
+        assert_eq!(error.code(), Some(&ErrorCode::InsufficientHoldings));
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+        assert!(!error.is_retryable());
+
+        let body = b"<html>502 Bad Gateway</html>";
+        let error = request.parse(StatusCode::BAD_GATEWAY, body).unwrap_err();
+        assert!(
+            matches!(error, Error::Http(StatusCode::BAD_GATEWAY, ref body) if body.contains("502"))
+        );
+        assert!(error.is_retryable());
+    }
+}
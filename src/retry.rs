@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use chrono::prelude::*;
+use reqwest::header::{HeaderValue, RETRY_AFTER};
+use reqwest::StatusCode;
+
+use crate::error::Error;
+use crate::transport::HttpResponse;
+
+/// Policy for retrying failed requests
+///
+/// Requests rejected with `429 Too Many Requests` are retried after the delay given in the
+/// `Retry-After` header, since the API did not process them. Server errors, timeouts and
+/// connection errors are only retried for idempotent requests: GET and DELETE requests, and
+/// POST requests that carry an idempotency key.
+///
+/// Without a `Retry-After` header, the delay grows exponentially with every attempt, up to
+/// the maximum backoff, and is randomly shortened by up to half when jitter is enabled.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the first attempt
+    max_retries: u32,
+    /// Delay before the first retry
+    initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts
+    max_backoff: Duration,
+    /// Factor the delay grows by with every attempt
+    multiplier: u32,
+    /// Whether the delay is randomized
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default policy: 3 retries, starting at 500ms and doubling up to 30s
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self::default().max_retries(0)
+    }
+
+    /// Set the maximum number of retries after the first attempt
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the upper bound for the delay between two attempts
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the factor the delay grows by with every attempt
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Enable or disable randomizing the delay
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The delay before retry number `attempt + 1`, without jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Decide whether to retry after the given attempt, and how long to wait before doing so
+    pub(crate) fn delay<B>(
+        &self,
+        attempt: u32,
+        idempotent: bool,
+        result: &Result<HttpResponse<B>, Error>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        match result {
+            Ok(response) if response.status == StatusCode::TOO_MANY_REQUESTS => Some(
+                response
+                    .headers
+                    .get(RETRY_AFTER)
+                    .and_then(parse_retry_after)
+                    .unwrap_or_else(|| self.jittered(attempt)),
+            ),
+            Ok(response) if response.status.is_server_error() && idempotent => {
+                Some(self.jittered(attempt))
+            }
+            Err(Error::Reqwest(e)) if (e.is_connect() || e.is_timeout()) && idempotent => {
+                Some(self.jittered(attempt))
+            }
+            _ => None,
+        }
+    }
+
+    /// The backoff for `attempt`, randomly shortened by up to half if jitter is enabled
+    fn jittered(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        if !self.jitter {
+            return backoff;
+        }
+        let half = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+/// Parse a `Retry-After` header, given either in seconds or as an HTTP date
+pub(crate) fn parse_retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means the request can be retried right away
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use reqwest::StatusCode;
+
+    use super::{parse_retry_after, RetryPolicy};
+    use crate::transport::HttpResponse;
+
+    fn response(status: StatusCode, retry_after: Option<&'static str>) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+        if let Some(retry_after) = retry_after {
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(retry_after));
+        }
+        HttpResponse {
+            status,
+            headers,
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_up_to_the_maximum() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(5));
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_retry_decisions() {
+        let policy = RetryPolicy::new().max_retries(2).jitter(false);
+        let rate_limited = Ok(response(StatusCode::TOO_MANY_REQUESTS, Some("7")));
+        assert_eq!(
+            policy.delay(0, false, &rate_limited),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(policy.delay(2, false, &rate_limited), None);
+
+        let server_error = Ok(response(StatusCode::INTERNAL_SERVER_ERROR, None));
+        assert_eq!(
+            policy.delay(1, true, &server_error),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(policy.delay(1, false, &server_error), None);
+
+        let bad_request = Ok(response(StatusCode::BAD_REQUEST, None));
+        assert_eq!(policy.delay(0, true, &bad_request), None);
+        assert_eq!(RetryPolicy::none().delay(0, true, &server_error), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("12")),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(&HeaderValue::from_static("soon")), None);
+    }
+}
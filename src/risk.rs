@@ -0,0 +1,543 @@
+//! Pre-trade risk checks
+//!
+//! A [`RiskGuard`] predicts locally whether the API would reject an order, e.g. because the
+//! account cannot pay for it or the instrument is not tradable at the venue. Give one to
+//! [`TradingClientBuilder::risk_guard`](crate::builder::TradingClientBuilder::risk_guard), and
+//! [`post_order`](crate::client::TradingClient::post_order) checks every order before sending
+//! it. Failed checks come back as [`Error::RiskCheck`](crate::error::Error::RiskCheck), with
+//! every rule the order violates.
+//!
+//! The checks fetch the account, the positions, the instrument, the venue and, for market
+//! orders, the latest quote first, so a guarded order takes a few more requests to place.
+
+use std::fmt;
+
+use chrono::prelude::*;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::api::market_data::instruments::InstrumentInfo;
+use crate::api::market_data::venues::VenueData;
+use crate::api::orders::{OrderRequest, OrderType};
+use crate::api::trading::account::AccountResults;
+use crate::api::trading::positions::Position;
+use crate::api::{Mode, PaginationResponse};
+use crate::data_client::DATA_ENDPOINT;
+use crate::error::{Error, ErrorCode};
+use crate::identifiers::{Isin, Mic};
+use crate::money::{Amount, Price};
+use crate::request::Request;
+
+/// The highest estimated total of a buy order the API accepts, 25.000€
+pub const MAX_ORDER_TOTAL: Amount = Amount::from_raw(250_000_000);
+
+/// The venue orders without a venue are placed at
+static DEFAULT_VENUE: &str = "XMUN";
+
+/// Venue that only accepts orders in paper trading
+static PAPER_ONLY_VENUE: &str = "ALLDAY";
+
+/// Checks orders against the account and market data before they are sent
+///
+/// The estimated total of an order is its limit price, or else its stop price, times its
+/// quantity. Market buy orders are estimated from the ask price of the latest quote at their
+/// venue, and rejected if there is no quote to estimate from.
+#[derive(Debug, Clone)]
+pub struct RiskGuard {
+    /// Base url of the market data API the instrument and venue are fetched from
+    data_url: String,
+    /// The highest estimated total of a buy order
+    max_order_total: Amount,
+}
+
+impl Default for RiskGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RiskGuard {
+    /// A guard with the limits of the API, using the market data endpoint
+    pub fn new() -> Self {
+        Self {
+            data_url: DATA_ENDPOINT.to_string(),
+            max_order_total: MAX_ORDER_TOTAL,
+        }
+    }
+
+    /// Fetch instruments and venues from another market data url, e.g. a local mock server
+    pub fn data_url(mut self, data_url: impl Into<String>) -> Self {
+        self.data_url = data_url.into();
+        self
+    }
+
+    /// Reject buy orders with an estimated total above `max`. Defaults to [`MAX_ORDER_TOTAL`]
+    pub fn max_order_total(mut self, max: Amount) -> Self {
+        self.max_order_total = max;
+        self
+    }
+
+    /// Parse the market data url
+    pub(crate) fn parsed_data_url(&self) -> Result<Url, Error> {
+        Url::parse(&self.data_url)
+            .map_err(|e| Error::Config(format!("invalid data url {:?}: {}", self.data_url, e)))
+    }
+
+    /// Check an order against the fetched state of the account and market
+    pub(crate) fn check(&self, order: &OrderRequest, inputs: &RiskInputs) -> Result<(), Error> {
+        let mut violations = vec![];
+        let venue = venue_of(order);
+        let price = order
+            .limit_price
+            .or(order.stop_price)
+            .or(inputs.quote.as_ref().map(|quote| quote.ask));
+        let total = price.and_then(|price| price.checked_total(order.quantity));
+
+        match order.side {
+            OrderType::Buy if price.is_none() => {
+                violations.push(RiskViolation::NoQuote {
+                    isin: order.isin.clone(),
+                    venue: venue.clone(),
+                });
+            }
+            OrderType::Buy => {
+                // A total that does not fit an `Amount` is above every limit
+                let total = total.unwrap_or(Amount::from_raw(i64::MAX));
+                let available = inputs.account.cash_to_invest;
+                if total > available {
+                    violations.push(RiskViolation::InsufficientFunds {
+                        required: total,
+                        available,
+                    });
+                }
+                if total > self.max_order_total {
+                    violations.push(RiskViolation::TotalPriceLimitExceeded {
+                        total,
+                        limit: self.max_order_total,
+                    });
+                }
+            }
+            OrderType::Sell => {
+                let held = inputs
+                    .positions
+                    .iter()
+                    .filter(|position| position.isin == order.isin)
+                    .map(|position| position.quantity)
+                    .sum();
+                if held < order.quantity {
+                    violations.push(RiskViolation::InsufficientHoldings {
+                        held,
+                        requested: order.quantity,
+                    });
+                }
+            }
+        }
+
+        if inputs.account.mode == Mode::Live && venue == PAPER_ONLY_VENUE {
+            violations.push(RiskViolation::ForbiddenForVenue {
+                venue: venue.clone(),
+            });
+        }
+
+        match &inputs.instrument {
+            None => violations.push(RiskViolation::InstrumentNotFound {
+                isin: order.isin.clone(),
+            }),
+            Some(instrument) => {
+                let tradable = instrument
+                    .venues
+                    .iter()
+                    .flatten()
+                    .any(|listing| listing.mic == venue && listing.tradable);
+                if !tradable {
+                    violations.push(RiskViolation::InstrumentNotTradable {
+                        isin: order.isin.clone(),
+                        venue: venue.clone(),
+                    });
+                }
+            }
+        }
+
+        match &inputs.venue {
+            None => violations.push(RiskViolation::VenueNotFound { venue }),
+            Some(data) if !data.is_open => {
+                let next_opening = data
+                    .opening_days
+                    .iter()
+                    .filter(|day| **day >= inputs.today)
+                    .min()
+                    .copied();
+                if let Some(expires_at) = order.expires_at {
+                    if next_opening.is_none_or(|opening| expires_at < opening) {
+                        violations.push(RiskViolation::ExpiresBeforeOpening {
+                            expires_at,
+                            next_opening,
+                        });
+                    }
+                }
+            }
+            Some(_) => {}
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(Error::RiskCheck(RiskViolations(violations))),
+        }
+    }
+}
+
+/// The state of the account and market an order is checked against
+pub(crate) struct RiskInputs {
+    /// The account the order is placed for
+    pub(crate) account: AccountResults,
+    /// Positions of the account. Only fetched for sell orders
+    pub(crate) positions: Vec<Position>,
+    /// The instrument of the order, if it exists
+    pub(crate) instrument: Option<InstrumentInfo>,
+    /// The venue of the order, if it exists
+    pub(crate) venue: Option<VenueData>,
+    /// The latest quote of the instrument at the venue. Only fetched for market buy orders
+    pub(crate) quote: Option<Quote>,
+    /// The current day
+    pub(crate) today: NaiveDate,
+}
+
+/// The venue an order is placed at
+pub(crate) fn venue_of(order: &OrderRequest) -> Mic {
+    order
+        .venue
+        .clone()
+        .unwrap_or_else(|| DEFAULT_VENUE.parse().expect("valid MIC"))
+}
+
+/// Request for the instrument of an order
+pub(crate) fn instrument_request(
+    order: &OrderRequest,
+) -> Result<Request<PaginationResponse<InstrumentInfo>>, Error> {
+    Request::get("instruments/").query(&[("isin", &order.isin)])
+}
+
+/// The latest prices of an instrument at a venue
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Quote {
+    /// The price buyers pay
+    #[serde(rename = "a")]
+    pub(crate) ask: Price,
+}
+
+/// Whether the order has no price of its own, so its total is estimated from a quote
+pub(crate) fn needs_quote(order: &OrderRequest) -> bool {
+    order.side == OrderType::Buy && order.limit_price.is_none() && order.stop_price.is_none()
+}
+
+/// Request for the latest quote of the instrument of an order at its venue
+pub(crate) fn quote_request(
+    order: &OrderRequest,
+) -> Result<Request<PaginationResponse<Quote>>, Error> {
+    let query = (("isin", &order.isin), ("mic", venue_of(order)));
+    Request::get("quotes/latest").query(&query)
+}
+
+/// Request for the venue of an order
+pub(crate) fn venue_request(
+    order: &OrderRequest,
+) -> Result<Request<PaginationResponse<VenueData>>, Error> {
+    Request::get("venues/").query(&[("mic", venue_of(order))])
+}
+
+/// A rule an order violates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskViolation {
+    /// The account does not have enough cash to invest for the buy order
+    InsufficientFunds {
+        /// The estimated total of the order
+        required: Amount,
+        /// The cash to invest of the account
+        available: Amount,
+    },
+    /// The account holds fewer shares than the sell order sells
+    InsufficientHoldings {
+        /// The amount of shares the account holds
+        held: i64,
+        /// The amount of shares the order sells
+        requested: i64,
+    },
+    /// The estimated total of the buy order is above the limit
+    TotalPriceLimitExceeded {
+        /// The estimated total of the order
+        total: Amount,
+        /// The highest total allowed
+        limit: Amount,
+    },
+    /// The order expires before the venue opens again
+    ExpiresBeforeOpening {
+        /// The expiry date of the order
+        expires_at: NaiveDate,
+        /// The next day the venue opens, if it has any upcoming opening days
+        next_opening: Option<NaiveDate>,
+    },
+    /// Live orders can not be placed at the venue
+    ForbiddenForVenue {
+        /// The venue of the order
+        venue: Mic,
+    },
+    /// The instrument does not exist
+    InstrumentNotFound {
+        /// ISIN of the order
+        isin: Isin,
+    },
+    /// The instrument is not tradable at the venue of the order
+    InstrumentNotTradable {
+        /// ISIN of the order
+        isin: Isin,
+        /// The venue of the order
+        venue: Mic,
+    },
+    /// The venue does not exist
+    VenueNotFound {
+        /// The venue of the order
+        venue: Mic,
+    },
+    /// The market buy order can not be estimated, because the instrument has no quote at
+    /// the venue
+    NoQuote {
+        /// ISIN of the order
+        isin: Isin,
+        /// The venue of the order
+        venue: Mic,
+    },
+}
+
+impl RiskViolation {
+    /// The error code the API would reject the order with
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            RiskViolation::InsufficientFunds { .. } => ErrorCode::AccountInsufficientFunds,
+            RiskViolation::InsufficientHoldings { .. } => ErrorCode::InsufficientHoldings,
+            RiskViolation::TotalPriceLimitExceeded { .. } => {
+                ErrorCode::OrderTotalPriceLimitExceeded
+            }
+            RiskViolation::ExpiresBeforeOpening { .. } => ErrorCode::OrderExpirationDateInvalid,
+            RiskViolation::ForbiddenForVenue { .. } => ErrorCode::ForbiddenForVenue,
+            RiskViolation::InstrumentNotFound { .. } => ErrorCode::InstrumentNotFound,
+            RiskViolation::InstrumentNotTradable { .. } => ErrorCode::InstrumentNotTradable,
+            RiskViolation::VenueNotFound { .. } => ErrorCode::VenueNotFound,
+            RiskViolation::NoQuote { .. } => ErrorCode::InstrumentNotTradable,
+        }
+    }
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskViolation::InsufficientFunds {
+                required,
+                available,
+            } => write!(
+                f,
+                "order costs {required}, but only {available} are available"
+            ),
+            RiskViolation::InsufficientHoldings { held, requested } => {
+                write!(
+                    f,
+                    "order sells {requested} shares, but only {held} are held"
+                )
+            }
+            RiskViolation::TotalPriceLimitExceeded { total, limit } => {
+                write!(f, "order total {total} is above the limit of {limit}")
+            }
+            RiskViolation::ExpiresBeforeOpening {
+                expires_at,
+                next_opening: Some(opening),
+            } => write!(
+                f,
+                "order expires on {expires_at}, before the venue opens on {opening}"
+            ),
+            RiskViolation::ExpiresBeforeOpening { expires_at, .. } => write!(
+                f,
+                "order expires on {expires_at}, and the venue has no upcoming opening days"
+            ),
+            RiskViolation::ForbiddenForVenue { venue } => {
+                write!(f, "live orders can not be placed at {venue}")
+            }
+            RiskViolation::InstrumentNotFound { isin } => write!(f, "instrument {isin} not found"),
+            RiskViolation::InstrumentNotTradable { isin, venue } => {
+                write!(f, "instrument {isin} is not tradable at {venue}")
+            }
+            RiskViolation::VenueNotFound { venue } => write!(f, "venue {venue} not found"),
+            RiskViolation::NoQuote { isin, venue } => {
+                write!(
+                    f,
+                    "instrument {isin} has no quote at {venue} to price the order"
+                )
+            }
+        }
+    }
+}
+
+/// All rules an order violates. Never empty
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskViolations(Vec<RiskViolation>);
+
+impl RiskViolations {
+    /// The violated rules
+    pub fn violations(&self) -> &[RiskViolation] {
+        &self.0
+    }
+}
+
+impl fmt::Display for RiskViolations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::*;
+
+    use super::{RiskGuard, RiskViolation};
+    use crate::api::orders::OrderRequest;
+    use crate::api::Mode;
+    use crate::client::TradingClient;
+    use crate::error::{Error, ErrorCode};
+    use crate::identifiers::Isin;
+    use crate::mock::{MockServer, MOCK_API_KEY};
+    use crate::money::{Amount, Price};
+
+    /// A client for the mock server that checks orders with the default guard
+    fn guarded_client(server: &MockServer) -> TradingClient {
+        TradingClient::builder(MOCK_API_KEY)
+            .base_url(server.url())
+            .risk_guard(RiskGuard::new().data_url(server.url()))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_violations_are_collected() {
+        let server = MockServer::start().unwrap();
+        let client = guarded_client(&server);
+        let apple = "US0378331005".parse().unwrap();
+        let order = OrderRequest::buy(apple, 200).limit(Price::from_euros(150).unwrap());
+
+        let Err(Error::RiskCheck(violations)) = client.post_order(order) else {
+            panic!("expected the risk checks to fail");
+        };
+        let codes: Vec<ErrorCode> = violations
+            .violations()
+            .iter()
+            .map(RiskViolation::error_code)
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ErrorCode::AccountInsufficientFunds,
+                ErrorCode::OrderTotalPriceLimitExceeded
+            ]
+        );
+        assert!(server.state().orders.is_empty());
+    }
+
+    #[test]
+    fn test_market_orders_are_estimated_from_the_quote() {
+        let server = MockServer::start().unwrap();
+        let client = guarded_client(&server);
+        let apple: Isin = "US0378331005".parse().unwrap();
+
+        // 200 shares at 150€ are above both the cash and the total limit
+        let Err(Error::RiskCheck(violations)) =
+            client.check_order(&OrderRequest::buy(apple.clone(), 200))
+        else {
+            panic!("expected the risk checks to fail");
+        };
+        assert_eq!(
+            violations.violations()[0],
+            RiskViolation::InsufficientFunds {
+                required: Amount::from_euros(30_000).unwrap(),
+                available: Amount::from_euros(10_000).unwrap(),
+            }
+        );
+        assert_eq!(violations.violations().len(), 2);
+        assert!(client
+            .check_order(&OrderRequest::buy(apple.clone(), 2))
+            .is_ok());
+
+        server.state().prices.clear();
+        let Err(Error::RiskCheck(violations)) = client.check_order(&OrderRequest::buy(apple, 2))
+        else {
+            panic!("expected the risk checks to fail");
+        };
+        assert!(matches!(
+            violations.violations(),
+            [RiskViolation::NoQuote { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_live_orders_are_forbidden_on_allday() {
+        let server = MockServer::start().unwrap();
+        let client = guarded_client(&server);
+        let order = OrderRequest::buy("US0378331005".parse().unwrap(), 1)
+            .limit(Price::from_euros(150).unwrap())
+            .venue("ALLDAY".parse().unwrap());
+
+        let violations = |client: &TradingClient| match client.check_order(&order) {
+            Err(Error::RiskCheck(violations)) => violations.violations().to_vec(),
+            _ => vec![],
+        };
+        let forbidden = RiskViolation::ForbiddenForVenue {
+            venue: "ALLDAY".parse().unwrap(),
+        };
+        assert!(!violations(&client).contains(&forbidden));
+        server.state().account.mode = Mode::Live;
+        assert!(violations(&client).contains(&forbidden));
+    }
+
+    #[test]
+    fn test_holdings_venue_and_opening_days() {
+        let server = MockServer::start().unwrap();
+        let client = guarded_client(&server);
+        let sap = "DE0007164600".parse().unwrap();
+
+        let error = client.post_order(OrderRequest::sell(sap, 1)).unwrap_err();
+        let Error::RiskCheck(violations) = error else {
+            panic!("expected the risk checks to fail");
+        };
+        assert_eq!(
+            violations.violations(),
+            [RiskViolation::InsufficientHoldings {
+                held: 0,
+                requested: 1
+            }]
+        );
+
+        let unknown =
+            OrderRequest::buy("DE0007164600".parse().unwrap(), 1).venue("XFRA".parse().unwrap());
+        let error = client.post_order(unknown).unwrap_err();
+        assert!(error.to_string().contains("venue XFRA not found"));
+
+        let today = Utc::now().date_naive();
+        server.state().venues[0].is_open = false;
+        server.state().venues[0].opening_days = vec![today + chrono::Duration::days(2)];
+        let order = OrderRequest::buy("DE0007164600".parse().unwrap(), 1).expires(today);
+        let Err(Error::RiskCheck(violations)) = client.check_order(&order) else {
+            panic!("expected the risk checks to fail");
+        };
+        assert_eq!(
+            violations.violations()[0].error_code(),
+            ErrorCode::OrderExpirationDateInvalid
+        );
+        let order = order.expires(today + chrono::Duration::days(2));
+        assert!(client.check_order(&order).is_ok());
+        assert!(client
+            .post_order(order.limit(Price::from_euros(120).unwrap()))
+            .is_ok());
+    }
+}
@@ -0,0 +1,274 @@
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{Cursor, Read};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+
+#[cfg(feature = "async")]
+use futures_util::{stream, Stream};
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode, Url};
+
+use crate::error::Error;
+
+/// An HTTP request, as the clients hand it to a [`Transport`]
+///
+/// The `Authorization` header is already set.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// HTTP method of the request
+    pub method: Method,
+    /// Full url of the request, including the query
+    pub url: Url,
+    /// Headers of the request
+    pub headers: HeaderMap,
+    /// Json encoded body
+    pub body: Option<String>,
+}
+
+/// An HTTP response, as a [`Transport`] hands it back to the clients
+///
+/// The body is buffered, unless the response comes from `send_streaming`.
+#[derive(Debug, Clone)]
+pub struct HttpResponse<B = Vec<u8>> {
+    /// HTTP status of the response
+    pub status: StatusCode,
+    /// Headers of the response
+    pub headers: HeaderMap,
+    /// The raw response body
+    pub body: B,
+}
+
+/// The unread body of a response from [`Transport::send_streaming`]
+pub type BodyReader = Box<dyn Read + Send>;
+
+/// The unread body of a response from [`AsyncTransport::send_streaming`], in chunks
+#[cfg(feature = "async")]
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, Error>> + Send>>;
+
+/// Sends HTTP requests for the blocking clients
+///
+/// Implement this to plug in another HTTP stack, or an in-memory transport that returns
+/// canned responses in tests. Retries and rate limiting are handled by the clients, so a
+/// transport only sends a single request.
+pub trait Transport {
+    /// Send the request and return the response, whatever its status
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error>;
+
+    /// Send the request and return the response with its body unread, e.g. for downloads
+    ///
+    /// The default reads the whole body with [`send`](Self::send) first.
+    fn send_streaming(&self, request: HttpRequest) -> Result<HttpResponse<BodyReader>, Error> {
+        let response = self.send(request)?;
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body: Box::new(Cursor::new(response.body)),
+        })
+    }
+}
+
+/// Sends HTTP requests for the async clients
+///
+/// The async counterpart of [`Transport`].
+#[cfg(feature = "async")]
+pub trait AsyncTransport {
+    /// Send the request and return the response, whatever its status
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse, Error>> + Send;
+
+    /// Send the request and return the response with its body unread, e.g. for downloads
+    ///
+    /// The default reads the whole body with [`send`](Self::send) first.
+    fn send_streaming(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse<BodyStream>, Error>> + Send {
+        let response = self.send(request);
+        async move {
+            let response = response.await?;
+            let body: BodyStream = Box::pin(stream::iter([Ok(response.body)]));
+            Ok(HttpResponse {
+                status: response.status,
+                headers: response.headers,
+                body,
+            })
+        }
+    }
+}
+
+/// The default transport of the blocking clients, built on `reqwest::blocking::Client`
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    /// Internal client used for all requests.
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport from a configured reqwest client
+    pub fn new(client: reqwest::blocking::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send()?;
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes()?.to_vec(),
+        })
+    }
+
+    fn send_streaming(&self, request: HttpRequest) -> Result<HttpResponse<BodyReader>, Error> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send()?;
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Box::new(response),
+        })
+    }
+}
+
+/// The default transport of the async clients, built on `reqwest::Client`
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Default)]
+pub struct AsyncReqwestTransport {
+    /// Internal client used for all requests.
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "async")]
+impl AsyncReqwestTransport {
+    /// Create a transport from a configured reqwest client
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransport for AsyncReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send().await?;
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes().await?.to_vec(),
+        })
+    }
+
+    async fn send_streaming(
+        &self,
+        request: HttpRequest,
+    ) -> Result<HttpResponse<BodyStream>, Error> {
+        let mut builder = self
+            .client
+            .request(request.method, request.url)
+            .headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = stream::unfold(Some(response), |response| async move {
+            let mut response = response?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk.to_vec()), Some(response))),
+                Ok(None) => None,
+                // Nothing follows an error
+                Err(e) => Some((Err(e.into()), None)),
+            }
+        });
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: Box::pin(body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use reqwest::header::{HeaderMap, AUTHORIZATION};
+    use reqwest::StatusCode;
+
+    use super::{HttpRequest, HttpResponse, Transport};
+    use crate::api::trading::positions::PositionQuery;
+    use crate::client::TradingClient;
+    use crate::error::Error;
+
+    /// Returns the same canned response to every request, and remembers the requests
+    #[derive(Default)]
+    struct CannedTransport {
+        body: &'static str,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl Transport for CannedTransport {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+            self.requests.lock().unwrap().push(request);
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: self.body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_client_sends_through_transport() {
+        let transport = CannedTransport {
+            body: r#"{
+                "time": "2022-02-14T20:44:03.759+00:00",
+                "status": "ok",
+                "mode": "paper",
+                "results": [],
+                "previous": null,
+                "next": null,
+                "total": 0,
+                "page": 1,
+                "pages": 1
+            }"#,
+            ..Default::default()
+        };
+        let client = TradingClient::builder("key")
+            .build_with_transport(transport)
+            .unwrap();
+        let positions = client.get_positions(PositionQuery::new()).unwrap();
+        assert_eq!(positions.total, 0);
+
+        let requests = client.transport.requests.lock().unwrap();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://paper-trading.lemon.markets/v1/positions/"
+        );
+        assert_eq!(requests[0].headers[AUTHORIZATION], "Bearer key");
+    }
+}